@@ -6,12 +6,19 @@ pub mod core {
     use super::*;
     use std::fmt::Display;
     use crate::stan_command::WithDefaultArg;
-    #[derive(Debug, Clone, PartialEq)]
+    #[derive(Debug, Clone)]
     pub enum ArgPath {
         Borrowed(&'static str),
         Owned(PathBuf),
     }
 
+    /// equal when the paths are, whether borrowed or owned
+    impl PartialEq for ArgPath {
+        fn eq(&self, other: &Self) -> bool {
+            self.as_path() == other.as_path()
+        }
+    }
+
     impl WithDefaultArg for ArgPath {
         const ARG_DEFAULT: Self = Self::Borrowed("");
     }
@@ -34,12 +41,19 @@ pub mod core {
         }
     }
 
-    #[derive(Debug, Clone, PartialEq)]
+    #[derive(Debug, Clone)]
     pub enum ArgWritablePath {
         Borrowed(&'static str),
         Owned(PathBuf),
     }
 
+    /// equal when the paths are, whether borrowed or owned
+    impl PartialEq for ArgWritablePath {
+        fn eq(&self, other: &Self) -> bool {
+            self.as_path() == other.as_path()
+        }
+    }
+
     impl WithDefaultArg for ArgWritablePath {
         const ARG_DEFAULT: Self = Self::Borrowed("");
     }
@@ -62,12 +76,19 @@ pub mod core {
         }
     }
 
-    #[derive(Debug, Clone, PartialEq)]
+    #[derive(Debug, Clone)]
     pub enum ArgReadablePath {
         Borrowed(&'static str),
         Owned(PathBuf),
     }
 
+    /// equal when the paths are, whether borrowed or owned
+    impl PartialEq for ArgReadablePath {
+        fn eq(&self, other: &Self) -> bool {
+            self.as_path() == other.as_path()
+        }
+    }
+
     impl WithDefaultArg for ArgReadablePath {
         const ARG_DEFAULT: Self = Self::Borrowed("");
     }
@@ -218,6 +239,35 @@ impl ArgPath {
 }
 
 impl ArgWritablePath {
+    /// append `_{suffix}` to the file stem, e.g. `out/output.csv` -> `out/output_2.csv`
+    ///
+    /// this is how CmdStan names the per-chain files of multi-chain runs
+    pub fn with_stem_suffix(&self, suffix: &str) -> Self {
+        let path = self.as_path();
+        let mut name = path.file_stem().map(|s| s.to_os_string()).unwrap_or_default();
+        name.push("_");
+        name.push(suffix);
+        if let Some(ext) = path.extension() {
+            name.push(".");
+            name.push(ext);
+        }
+        Self::Owned(path.with_file_name(name))
+    }
+
+    /// the file of this path in `dir`
+    pub fn moved_into(&self, dir: &Path) -> Self {
+        Self::Owned(dir.join(self.as_path().file_name().unwrap_or_default()))
+    }
+
+    /// a bare file name put in `dir`, a path with a directory as it is
+    pub fn resolved_in(&self, dir: &Path) -> Self {
+        let is_bare = self.as_path().parent().is_none_or(|p| p.as_os_str().is_empty());
+        match is_bare && !dir.as_os_str().is_empty() {
+            true => self.moved_into(dir),
+            false => self.clone(),
+        }
+    }
+
     pub fn write_once(&self, text: &str) -> Result<&Self, Error> {
         let path: &Path = match self {
             ArgWritablePath::Borrowed(p) => Path::new(p),
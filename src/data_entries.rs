@@ -51,7 +51,7 @@ pub mod data_entry {
         }
     }
 
-    #[derive(Debug, Clone, Default)]
+    #[derive(Debug, Clone, Default, PartialEq)]
     pub struct DataEntries {
         pub datas: Vec<(String, DataEntry)>,
     }
@@ -65,6 +65,10 @@ pub mod data_entry {
             self.datas.push((name.to_string(), entry.into()));
             self
         }
+
+        pub fn get_entry(&self, name: &str) -> Option<&DataEntry> {
+            self.datas.iter().find(|(n, _)| n == name).map(|(_, e)| e)
+        }
     }
 }
 
@@ -1,5 +1,5 @@
 use std::{fmt::{Display, Formatter, self}, error::Error};
-pub use {data_collection_error::DataCollectionError, arg_error::ArgError, param_error::ParamError, file_error::FileError, cmd_stan_error::CmdStanError};
+pub use {data_collection_error::DataCollectionError, arg_error::{ArgError, Interruption}, runtime_error::RuntimeError, param_error::ParamError, file_error::FileError, cmd_stan_error::CmdStanError};
 
 mod data_collection_error {
     use super::*;
@@ -20,13 +20,41 @@ mod data_collection_error {
 }
 
 mod arg_error {
-    use std::{error::Error, fmt::Display};
+    use std::{error::Error, fmt::Display, path::PathBuf, time::Duration};
+
+    /// why a run was stopped before the process finished
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Interruption {
+        Timeout(Duration),
+        Cancelled,
+    }
+
+    impl Display for Interruption {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Timeout(d) => write!(f, "timed out after {d:?}"),
+                Self::Cancelled => write!(f, "cancelled"),
+            }
+        }
+    }
 
     #[derive(Debug)]
     pub enum ArgError {
         NotValidArgTreeType(String),
         BadArgumentValue(String),
         FileSystemError(std::io::Error),
+        /// the process was killed, the partial output is kept at the path
+        Interrupted(Interruption, PathBuf),
+        /// the process exited unsuccessfully
+        Runtime(super::RuntimeError, std::process::Output),
+        /// a CmdStan argument string couldn't be parsed: (index of the token, token, message)
+        BadArgString(usize, String, String),
+        /// the values of the arg tree don't fit together, every problem found
+        InvalidCombination(Vec<String>),
+        /// a JSON or TOML config couldn't be read
+        BadConfig(String),
+        /// the CmdStan version lacks arguments of the arg tree: (version, [(argument, first version supporting it)])
+        Unsupported(crate::stan_command::CmdStanVersion, Vec<(String, crate::stan_command::CmdStanVersion)>),
     }
 
     impl Display for ArgError {
@@ -35,6 +63,15 @@ mod arg_error {
                 Self::NotValidArgTreeType(s) => write!(f, "{s}"),
                 Self::BadArgumentValue(s) => write!(f, "{s}"),
                 Self::FileSystemError(e) => write!(f, "file system error: {e}"),
+                Self::Interrupted(i, p) => write!(f, "run {i}, partial output kept in {p:?}"),
+                Self::Runtime(e, _) => write!(f, "{e}"),
+                Self::BadArgString(i, token, msg) => write!(f, "argument {i} `{token}`: {msg}"),
+                Self::InvalidCombination(problems) => write!(f, "invalid arguments: {}", problems.join("; ")),
+                Self::BadConfig(s) => write!(f, "bad config: {s}"),
+                Self::Unsupported(version, args) => {
+                    let args: Vec<_> = args.iter().map(|(a, v)| format!("{a} (since {v})")).collect();
+                    write!(f, "CmdStan {version} doesn't support {}", args.join(", "))
+                }
             }
         }
     }
@@ -42,6 +79,87 @@ mod arg_error {
     impl Error for ArgError {}
 }
 
+mod runtime_error {
+    use super::*;
+
+    /// A classified error of a CmdStan executable which exited unsuccessfully.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum RuntimeError {
+        /// a variable declared in the data block is missing in the data file: (variable, message)
+        MissingData(String, String),
+        /// the dimensions of a data variable don't match its declaration: (variable, message)
+        DimensionMismatch(String, String),
+        /// a data variable violates its declared constraint: (variable, message)
+        DataConstraint(String, String),
+        /// no initial value with finite log density and gradient was found: (message)
+        InitializationFailed(String),
+        /// CmdStan rejected a command line argument: (argument, message)
+        BadArgument(String, String),
+        /// anything else: (the last line printed)
+        Other(String),
+    }
+
+    impl Display for RuntimeError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::MissingData(v, m) => write!(f, "missing data variable {v}: {m}"),
+                Self::DimensionMismatch(v, m) => write!(f, "dimension mismatch of data variable {v}: {m}"),
+                Self::DataConstraint(v, m) => write!(f, "constraint violated by data variable {v}: {m}"),
+                Self::InitializationFailed(m) => write!(f, "initialization failed: {m}"),
+                Self::BadArgument(a, m) => write!(f, "bad argument {a}: {m}"),
+                Self::Other(m) => write!(f, "stan runtime error: {m}"),
+            }
+        }
+    }
+
+    impl Error for RuntimeError {}
+
+    /// the value of `key=value;` in a CmdStan exception message
+    fn field_of(line: &str, key: &str) -> Option<String> {
+        let (_, rest) = line.split_once(key)?;
+        Some(rest.split(';').next()?.trim().to_string())
+    }
+
+    impl RuntimeError {
+        /// Classify the output of a failed run.
+        pub fn classify(output: &std::process::Output) -> Self {
+            Self::classify_text(&String::from_utf8_lossy(&output.stdout), &String::from_utf8_lossy(&output.stderr))
+        }
+
+        pub fn classify_text(stdout: &str, stderr: &str) -> Self {
+            let text = format!("{stderr}\n{stdout}");
+            let lines: Vec<&str> = text.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+            let find = |pat: &str| lines.iter().find(|l| l.contains(pat)).map(|l| l.to_string());
+
+            if let Some(l) = find("is not a valid value for") {
+                let argument = l.split('"').nth(1).unwrap_or_default().to_string();
+                return Self::BadArgument(argument, l);
+            }
+            if let Some(l) = find("is either mistyped or misplaced") {
+                let argument = l.split_whitespace().next().unwrap_or_default().to_string();
+                return Self::BadArgument(argument, l);
+            }
+            if let Some(l) = find("Initialization between").or_else(|| find("Initialization failed")) {
+                return Self::InitializationFailed(l);
+            }
+            if let Some(l) = find("variable does not exist") {
+                return Self::MissingData(field_of(&l, "variable name=").unwrap_or_default(), l);
+            }
+            if let Some(l) = find("mismatch in dimension").or_else(|| find("mismatch in number dimensions")) {
+                return Self::DimensionMismatch(field_of(&l, "variable name=").unwrap_or_default(), l);
+            }
+            if let Some(l) = find(", but must be") {
+                // ... model_namespace::model: N is -1, but must be greater than or equal to 0 (in ...)
+                let head = l.split(", but must be").next().unwrap_or_default();
+                let head = head.rsplit(": ").next().unwrap_or_default();
+                let variable = head.split(" is ").next().unwrap_or_default().trim().to_string();
+                return Self::DataConstraint(variable, l);
+            }
+            Self::Other(lines.last().map(|l| l.to_string()).unwrap_or_default())
+        }
+    }
+}
+
 mod param_error {
     use super::*;
 
@@ -74,6 +192,7 @@ mod file_error {
     use super::*;
     use std::path::PathBuf;
     use crate::init::STAN_HOME_KEY;
+    use crate::stan_model::StanLint;
     #[derive(Debug)]
     pub enum FileError {
         FileSystem(std::io::Error),
@@ -81,6 +200,57 @@ mod file_error {
         EnvVar(std::env::VarError),
         InvalidPath(String, PathBuf),
         BadFileFormat(String, PathBuf),
+        LockTimeout(PathBuf, std::time::Duration),
+        Stanc(std::process::Output),
+        Lint(Vec<StanLint>),
+        Linking(Vec<String>, std::process::Output),
+    }
+
+    impl FileError {
+        /// Classify the output of a failed `make`.
+        ///
+        /// returns FileError::Linking with the undefined symbols if the linker failed, such as when
+        /// a function declared in the model is missing from the user header; otherwise FileError::Compilation.
+        pub fn from_compilation(output: std::process::Output) -> Self {
+            match Self::undefined_symbols(&String::from_utf8_lossy(&output.stderr)) {
+                Some(symbols) => FileError::Linking(symbols, output),
+                None => FileError::Compilation(output),
+            }
+        }
+
+        /// the undefined symbols in the stderr of `make`, None if the linker didn't fail
+        pub(crate) fn undefined_symbols(stderr: &str) -> Option<Vec<String>> {
+            let mut symbols: Vec<String> = Vec::new();
+            let mut lines = stderr.lines();
+            while let Some(line) = lines.next() {
+                let symbol = if let Some((_, rest)) = line.split_once("undefined reference to ") {
+                    // GNU ld: undefined reference to `foo(double)'
+                    Some(rest.trim().trim_start_matches(['`', '\'']).trim_end_matches('\'').to_string())
+                } else if line.starts_with("Undefined symbols") {
+                    // ld64: the symbols follow as   "_foo", referenced from:
+                    for l in lines.by_ref() {
+                        match l.trim().strip_suffix(", referenced from:") {
+                            Some(sym) => symbols.push(sym.trim_matches('"').to_string()),
+                            None if l.starts_with("ld:") => break,
+                            None => {}
+                        }
+                    }
+                    None
+                } else {
+                    None
+                };
+                if let Some(sym) = symbol
+                    && !symbols.contains(&sym) {
+                    symbols.push(sym);
+                }
+            }
+
+            if symbols.is_empty() && !stderr.contains("ld returned") && !stderr.contains("linker command failed") {
+                None
+            } else {
+                Some(symbols)
+            }
+        }
     }
 
     impl Display for FileError {
@@ -91,6 +261,13 @@ mod file_error {
                 FileError::FileSystem(e) => write!(f, "file system error: {e}"),
                 FileError::InvalidPath(s, p) => write!(f, "invalid filename: {s} {p:?}"),
                 FileError::BadFileFormat(s, p) => write!(f, "invalid file: {s} {p:?}"),
+                FileError::LockTimeout(p, d) => write!(f, "timed out after {d:?} waiting for build lock {p:?}"),
+                FileError::Stanc(e) => write!(f, "stanc error: {}", String::from_utf8_lossy(&e.stderr)),
+                FileError::Lint(lints) => {
+                    write!(f, "denied pedantic lints fired:")?;
+                    lints.iter().try_for_each(|l| write!(f, "\n{l}"))
+                }
+                FileError::Linking(symbols, _) => write!(f, "linking error, undefined symbols: {}", symbols.join(", ")),
             }
         }
     }
@@ -103,6 +280,10 @@ mod file_error {
                 FileError::FileSystem(e) => Some(e),
                 FileError::InvalidPath(_,_) => None,
                 FileError::BadFileFormat(_,_) => None,
+                FileError::LockTimeout(_,_) => None,
+                FileError::Stanc(_) => None,
+                FileError::Lint(_) => None,
+                FileError::Linking(_,_) => None,
             }
         }
     }
@@ -136,4 +317,56 @@ mod cmd_stan_error {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod runtime_error_test {
+    use super::RuntimeError;
+
+    #[test]
+    fn test_classify() {
+        let missing = "Exception: variable does not exist; processing stage=data initialization; variable name=N; base type=int";
+        assert_eq!(RuntimeError::classify_text("", missing), RuntimeError::MissingData("N".into(), missing.into()));
+
+        let dims = "Exception: mismatch in dimension declared and found in context; processing stage=data initialization; variable name=y; position=0; dims declared=(10); dims found=(5)";
+        assert_eq!(RuntimeError::classify_text("", dims), RuntimeError::DimensionMismatch("y".into(), dims.into()));
+
+        let constraint = "Exception: bernoulli_model_namespace::bernoulli_model: N is -1, but must be greater than or equal to 0 (in 'bernoulli.stan', line 2, column 2 to column 17)";
+        assert_eq!(RuntimeError::classify_text("", constraint), RuntimeError::DataConstraint("N".into(), constraint.into()));
+
+        let init = "Rejecting initial value:\n  Log probability evaluates to log(0)\nInitialization between (-2, 2) failed after 100 attempts. ";
+        assert!(matches!(RuntimeError::classify_text(init, ""), RuntimeError::InitializationFailed(_)));
+
+        let arg = "-1 is not a valid value for \"num_samples\"";
+        assert_eq!(RuntimeError::classify_text(arg, ""), RuntimeError::BadArgument("num_samples".into(), arg.into()));
+    }
+}
+
+#[cfg(test)]
+mod file_error_test {
+    use super::FileError;
+
+    #[test]
+    fn test_undefined_symbols() {
+        let gnu = "\
+/usr/bin/ld: /tmp/model.o: in function `model_namespace::model::log_prob_impl':
+model.hpp:120: undefined reference to `model_namespace::make_odds(double const&, std::ostream*)'
+model.hpp:140: undefined reference to `model_namespace::make_odds(double const&, std::ostream*)'
+collect2: error: ld returned 1 exit status
+make: *** [make/program:66: model] Error 1";
+        assert_eq!(FileError::undefined_symbols(gnu), Some(vec!["model_namespace::make_odds(double const&, std::ostream*)".to_string()]));
+
+        let ld64 = "\
+Undefined symbols for architecture arm64:
+  \"model_namespace::make_odds(double const&, std::ostream*)\", referenced from:
+      model_namespace::model::log_prob_impl(...) in model.o
+ld: symbol(s) not found for architecture arm64
+clang: error: linker command failed with exit code 1 (use -v to see invocation)";
+        assert_eq!(FileError::undefined_symbols(ld64), Some(vec!["model_namespace::make_odds(double const&, std::ostream*)".to_string()]));
+
+        let compiler = "\
+model.hpp:12:5: error: use of undeclared identifier 'make_odds'
+make: *** [model.o] Error 1";
+        assert_eq!(FileError::undefined_symbols(compiler), None);
+    }
+}
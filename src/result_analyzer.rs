@@ -41,6 +41,128 @@ pub fn analyze_csv<T:AsResult>(csv_file: ArgReadablePath, res: &mut T) -> Result
     Ok(())
 }
 
+/// A Stan CSV file: the column names, the rows of values, and the comments in between.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StanCsv {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<f64>>,
+    /// each comment without its `#`, with the number of rows read before it
+    pub comments: Vec<(usize, String)>,
+}
+
+impl StanCsv {
+    /// Parse the text of a Stan CSV file.
+    ///
+    /// Values such as `nan` and `inf` are kept as they parse; returns the message of the first bad line.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut res = Self::default();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if let Some(comment) = line.strip_prefix('#') {
+                res.comments.push((res.rows.len(), comment.trim().to_string()));
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if res.columns.is_empty() {
+                res.columns = line.split(',').map(|c| c.trim().to_string()).collect();
+                continue;
+            }
+            let row = line.split(',')
+                .map(|v| v.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("line {}: {e}", line_number+1))?;
+            if row.len() != res.columns.len() {
+                return Err(format!("Bad CSV Format: line {} has more or less columns than header", line_number+1));
+            }
+            res.rows.push(row);
+        }
+        Ok(res)
+    }
+
+    /// read and parse a Stan CSV file, see `parse`
+    pub fn read(csv_file: &std::path::Path) -> Result<Self, CmdStanError> {
+        let text = std::fs::read_to_string(csv_file).map_err(|e| CmdStanError::File(FileError::FileSystem(e)))?;
+        Self::parse(&text).map_err(|e| CmdStanError::File(FileError::BadFileFormat(e, csv_file.into())))
+    }
+
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c == name)
+    }
+
+    /// the values of the column `name`, one for each row
+    pub fn column(&self, name: &str) -> Option<Vec<f64>> {
+        let i = self.column_index(name)?;
+        Some(self.rows.iter().map(|r| r[i]).collect())
+    }
+
+    /// the value of the column `name` in the row `row`
+    pub fn get(&self, row: usize, name: &str) -> Option<f64> {
+        Some(*self.rows.get(row)?.get(self.column_index(name)?)?)
+    }
+
+    /// the comments read after `row` rows, e.g. the adaptation info which follows the warmup draws
+    pub fn comments_after(&self, row: usize) -> impl Iterator<Item = &str> {
+        self.comments.iter().filter(move |(r, _)| *r == row).map(|(_, c)| c.as_str())
+    }
+
+    /// split off the rows from `at`, keeping the columns and the comments read after them
+    pub fn split_rows_off(&mut self, at: usize) -> Self {
+        let at = at.min(self.rows.len());
+        let rows = self.rows.split_off(at);
+        let (before, after): (Vec<_>, Vec<_>) = std::mem::take(&mut self.comments).into_iter().partition(|(r, _)| *r <= at);
+        self.comments = before;
+        Self {
+            columns: self.columns.clone(),
+            rows,
+            comments: after.into_iter().map(|(r, c)| (r - at, c)).collect(),
+        }
+    }
+}
+
+/// The columns of the gradient table `diagnose` writes as comments, see `analyze_gradient_csv`.
+pub const GRADIENT_COLUMNS: [&str; 5] = ["param_idx", "value", "model", "finite_diff", "error"];
+
+/// Read the gradient table of a `diagnose` output file, one line per parameter.
+///
+/// CmdStan writes it as comments after the header:
+/// ```text
+/// # param idx           value           model     finite diff           error
+/// #         0          1.6518        -1.20832        -1.20832    -9.70445e-10
+/// ```
+/// the values are set with the keys of `GRADIENT_COLUMNS`.
+pub fn analyze_gradient_csv<T:AsResult>(csv_file: ArgReadablePath, res: &mut T) -> Result<(), CmdStanError> {
+    let file = std::fs::File::open(csv_file.as_path()).map_err(|e| CmdStanError::File(FileError::FileSystem(e)))?;
+    let buf = BufReader::new(file);
+    let mut in_table = false;
+
+    for line in buf.lines() {
+        let line = line.map_err(|e| CmdStanError::File(FileError::FileSystem(e)))?;
+        let line = line.trim().trim_start_matches('#').trim();
+        if line.starts_with("param idx") {
+            in_table = true;
+            continue;
+        }
+        if !in_table {
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != GRADIENT_COLUMNS.len() {
+            break;
+        }
+        res.new_line();
+        for (key, val) in GRADIENT_COLUMNS.iter().zip(parts) {
+            res.set_value(key, val).map_err(CmdStanError::Param)?;
+        }
+    }
+
+    if !in_table {
+        return Err(CmdStanError::File(FileError::BadFileFormat("no gradient table found".to_string(), csv_file.into())));
+    }
+    Ok(())
+}
+
 mod param_stream {
     use std::ops::{Deref, DerefMut};
     use crate::stan_model::WithParam;
@@ -115,10 +237,9 @@ mod param_stream {
         }
 
         fn set_value(&mut self, key: &str, val: &str) -> Result<(), ParamError> {
-            if let Some(item) = self.last_mut() {
-                if let Err(ParamError::ParseError(e)) = item.set_param_value(key, val) {
-                    return Err(ParamError::ParseError(e));
-                }
+            if let Some(item) = self.last_mut()
+                && let Err(ParamError::ParseError(e)) = item.set_param_value(key, val) {
+                return Err(ParamError::ParseError(e));
             }
             Ok(())
         }
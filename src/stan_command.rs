@@ -1,5 +1,8 @@
 #[macro_use]
 pub mod arg_tree;
+mod arg_node;
+mod arg_parse;
+mod arg_config;
 mod sample;
 mod optimize;
 mod common_arg;
@@ -9,9 +12,33 @@ mod generate_quantities;
 mod pathfinder;
 mod log_prob;
 mod laplace;
+mod runner;
+mod progress;
+mod run_options;
+mod dry_run;
+mod version;
+mod extra_args;
+mod method;
+mod fit;
+mod run_dir;
+mod chain_files;
+mod warm_start;
+
+pub use runner::{ChainRunner, ChainResults, RetryPolicy, RetryStrategy, FailedAttempt};
+pub use progress::{arg_into_with_progress, arg_into_with_channel, arg_into_with_options, StanEvent, ProgressEvent, ProgressPhase};
+pub use run_options::{RunOptions, CancelToken, incomplete_marker};
+pub use run_dir::{RunDirPolicy, RunDir};
+pub use chain_files::ChainFiles;
+pub use warm_start::{WarmStart, WarmupPolicy, inits_from_draw};
+pub use version::CmdStanVersion;
+pub use extra_args::WithExtraArgs;
+pub use method::StanMethod;
+pub use fit::{WithFit, StanFit, SampleFit, OptimizeFit, VariationalFit, DiagnoseFit, GradientCheck, Adaptation, InvMetric};
+pub use dry_run::{render_argv, render_shell_command, render_tree};
+pub use arg_node::{ArgNode, FromArgNode, FromArgValue, ToArgNode};
 
 use std::process::Command;
-use crate::error::ArgError;
+use crate::error::{ArgError, CmdStanError, RuntimeError};
 use arg_tree::{ArgPath, ArgReadablePath};
 
 #[non_exhaustive]
@@ -37,15 +64,131 @@ pub trait WithDefaultArg : PartialEq+Sized {
 pub trait ArgThrough {
     fn arg_type(&self) -> Result<ArgType, ArgError>;
     fn arg_through(&self, cmd: &mut Command) -> Result<(), ArgError>;
+
+    /// Same as `arg_through`, but without side effects: the files the arguments refer to, such as
+    /// the init files, are named but not written. `render_argv` uses this.
+    fn arg_through_dry(&self, cmd: &mut Command) -> Result<(), ArgError> {
+        self.arg_through(cmd)
+    }
+
+    /// the id of the run, which CmdStan uses as the chain id
+    fn get_id(&self) -> u32 {
+        1
+    }
+
     fn get_output_path(&self) -> Result<ArgPath, ArgError> {
         Err(ArgError::NotValidArgTreeType("no ArgOutput found in arg_tree, if this's costomized arg_tree structure, please impl this function.".to_string()))
     }
+
+    /// the number of chains one process runs, the `num_chains` argument of the method
+    fn num_chains(&self) -> u32 {
+        1
+    }
+
+    /// whether CmdStan writes the adapted metric next to the output file
+    fn saves_metric(&self) -> bool {
+        false
+    }
+
+    /// The files CmdStan writes for each chain, see `ChainFiles::plan`.
+    ///
+    /// By default, a single chain writing the file of `get_output_path`.
+    fn get_chain_files(&self) -> Result<Vec<ChainFiles>, ArgError> {
+        Ok(vec![ChainFiles::single(self.get_id(), self.get_output_path()?)])
+    }
+
+    /// push a message for each combination of values CmdStan would reject or misuse,
+    /// the checks which a single setter can't do
+    fn collect_problems(&self, problems: &mut Vec<String>) {}
+
+    /// push a message for each combination of values CmdStan accepts but adjusts or warns about
+    fn collect_warnings(&self, warnings: &mut Vec<String>) {}
+
+    /// push each argument this tree uses which recent CmdStan releases added, with the first version having it
+    fn collect_min_versions(&self, res: &mut Vec<(&'static str, CmdStanVersion)>) {}
+
+    /// Move every file this tree makes CmdStan write into `dir`, keeping the file names.
+    ///
+    /// `version` is the CmdStan running the tree if known, files whose argument it lacks are left unset.
+    fn route_files_into(&mut self, dir: &std::path::Path, version: Option<&CmdStanVersion>) {}
+
+    /// Check that CmdStan `version` has every argument used, `arg_into` calls this before spawning the model.
+    ///
+    /// # Errors
+    ///
+    /// ArgError::Unsupported with every argument `version` lacks
+    fn check_version(&self, version: &CmdStanVersion) -> Result<(), ArgError> {
+        let mut required = Vec::new();
+        self.collect_min_versions(&mut required);
+        let missing: Vec<_> = required.into_iter()
+            .filter(|(_, v)| v > version)
+            .map(|(a, v)| (a.to_string(), v))
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(ArgError::Unsupported(*version, missing))
+        }
+    }
+
+    /// Check the whole arg tree, `arg_into` calls this before spawning the model.
+    ///
+    /// # Errors
+    ///
+    /// ArgError::InvalidCombination with every problem found
+    fn validate(&self) -> Result<(), ArgError> {
+        let mut problems = Vec::new();
+        self.collect_problems(&mut problems);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ArgError::InvalidCombination(problems))
+        }
+    }
+
+    /// the warnings of `collect_warnings`, which don't stop `arg_into`
+    fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        self.collect_warnings(&mut warnings);
+        warnings
+    }
 }
 
+#[derive(Debug)]
 pub struct StanResult<T: ArgThrough> {
     pub arg_tree: T,
+    /// the output file of the first chain
     pub output_path: ArgReadablePath,
+    /// the files of every chain, ordered by chain id
+    pub chains: Vec<ChainFiles>,
     pub output: std::process::Output,
+    /// the directory made for the run by `RunOptions::run_dir`
+    pub run_dir: Option<RunDir>,
+    /// what CmdStan adjusted in the arguments, see `ArgThrough::warnings`
+    pub warnings: Vec<String>,
+}
+
+impl<T: ArgThrough+FromArgNode> StanResult<T> {
+    /// Rebuild the arguments recorded in the header of the output file.
+    ///
+    /// Compare it with `arg_tree` to check the run used the intended settings.
+    pub fn recorded_arg_tree(&self) -> Result<T, ArgError> {
+        T::from_csv_header(self.output_path.as_path())
+    }
+}
+
+impl<T: WithFit> StanResult<T> {
+    /// Read the output file into the result of the method, e.g. a `SampleFit` for `ArgSample`.
+    ///
+    /// only the first chain is read, see `fit_chains`
+    pub fn fit(&self) -> Result<T::Fit, CmdStanError> {
+        self.arg_tree.read_fit(self.output_path.as_path())
+    }
+
+    /// read the output file of every chain, in the order of `chains`
+    pub fn fit_chains(&self) -> Result<Vec<T::Fit>, CmdStanError> {
+        self.chains.iter().map(|c| self.arg_tree.read_fit(c.output.as_path())).collect()
+    }
 }
 
 /// Generates a `StanResult` from an argument tree (`arg_tree`) and an executable model path.
@@ -66,7 +209,7 @@ pub struct StanResult<T: ArgThrough> {
 /// # Returns
 /// - `Result<StanResult<T>, ArgError>`: Returns a `StanResult` containing the output of the model execution
 ///   or an `ArgError` if there is an issue during the process (e.g., invalid argument tree, file system 
-///   errors).
+///   errors). A model exiting unsuccessfully returns `ArgError::Runtime` with the classified error.
 ///
 /// # Example
 /// This example demonstrates how to use `arg_into` with a standard argument tree and model path:
@@ -90,13 +233,113 @@ pub struct StanResult<T: ArgThrough> {
 /// - The function uses the `Command` struct to execute the model, so the model must be executable
 ///   and properly set up in your environment.
 pub fn arg_into<T:ArgThrough+Clone>(arg_tree: &T, model_path: &ArgPath) -> Result<StanResult<T>, ArgError> {
+    let (mut cmd, output_path) = prepare_command(arg_tree, model_path)?;
+    let output = cmd.output().map_err(ArgError::FileSystemError)?;
+    finish_result(arg_tree, output_path, output)
+}
+
+/// build the command of `arg_tree` and check that the arg_tree has an output path
+fn prepare_command<T:ArgThrough>(arg_tree: &T, model_path: &ArgPath) -> Result<(Command, ArgPath), ArgError> {
     let output_path = arg_tree.get_output_path()?; // check wether the arg_tree is valid
+    arg_tree.validate()?;
+    if let Some(version) = CmdStanVersion::detect(model_path.as_path()) {
+        arg_tree.check_version(&version)?;
+    }
     let mut cmd = Command::new(model_path.as_path());
     arg_tree.arg_through(&mut cmd)?;
-    let output = cmd.output().map_err(ArgError::FileSystemError)?;
+    Ok((cmd, output_path))
+}
+
+/// check the exit status of the process and the files of every chain, and wrap up the result
+///
+/// `output_path` is only checked for a single chain, several chains write suffixed files instead
+fn finish_result<T:ArgThrough+Clone>(arg_tree: &T, output_path: ArgPath, output: std::process::Output) -> Result<StanResult<T>, ArgError> {
+    if !output.status.success() {
+        return Err(ArgError::Runtime(RuntimeError::classify(&output), output));
+    }
+    let chains = arg_tree.get_chain_files()?;
+    chains.iter().try_for_each(ChainFiles::verify)?;
+    let output_path = match chains.first() {
+        Some(c) if chains.len() > 1 => c.output.clone(),
+        _ => output_path,
+    };
     Ok(StanResult {
         arg_tree: arg_tree.clone(),
         output_path: output_path.into_readable().map_err(ArgError::FileSystemError)?,
+        chains,
         output,
+        run_dir: None,
+        warnings: arg_tree.warnings(),
     })
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod arg_through_test {
+    use super::*;
+    use sample::ArgSample;
+    use pathfinder::ArgPathfinder;
+    use log_prob::ArgLogProb;
+    use common_arg::WithCommonArgs;
+    use arg_tree::ArgWritablePath;
+
+    #[test]
+    fn test_validate() {
+        assert!(WithCommonArgs::new(ArgSample::new()).validate().is_ok());
+
+        let sample = ArgSample::new().with_num_samples(10).unwrap().with_thin(20).unwrap().with_num_warmup(100).unwrap();
+        match WithCommonArgs::new(sample.clone()).validate() {
+            Err(ArgError::InvalidCombination(problems)) => assert_eq!(problems.len(), 1),
+            r => panic!("expected InvalidCombination, found {r:?}"),
+        }
+        // CmdStan shrinks the default windows to fit a short warmup
+        let short_warmup = WithCommonArgs::new(ArgSample::new().with_num_warmup(100).unwrap());
+        assert!(short_warmup.validate().is_ok());
+        assert_eq!(short_warmup.warnings().len(), 1);
+        let mut sample = sample;
+        sample.adapt.set_window(50).unwrap();
+        match WithCommonArgs::new(sample).validate() {
+            Err(ArgError::InvalidCombination(problems)) => assert_eq!(problems.len(), 2),
+            r => panic!("expected InvalidCombination, found {r:?}"),
+        }
+
+        let pathfinder = ArgPathfinder::new().with_calculate_lp(false).with_num_draws(10).unwrap();
+        match pathfinder.validate() {
+            Err(ArgError::InvalidCombination(problems)) => assert_eq!(problems, [
+                "Pathfinder: psis_resample requires calculate_lp",
+                "Pathfinder: num_psis_draws (1000) exceeds num_draws * num_paths (10 * 4)",
+            ]),
+            r => panic!("expected InvalidCombination, found {r:?}"),
+        }
+
+        assert!(ArgLogProb::new().validate().is_err());
+        assert!(ArgLogProb::new().with_constrained_params(ArgReadablePath::Borrowed("p.json")).validate().is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_result_warnings() {
+        let dir = std::env::temp_dir().join(format!("simple_cmdstan_warnings_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("output.csv"), "lp__\n0\n").unwrap();
+        let mut args = WithCommonArgs::new(ArgSample::new().with_num_warmup(100).unwrap());
+        args.output.set_file(ArgWritablePath::Owned(dir.join("output.csv")));
+        let output = Command::new("true").output().unwrap();
+        let res = finish_result(&args, args.get_output_path().unwrap(), output);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(res.unwrap().warnings, args.warnings());
+        assert_eq!(args.warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_check_version() {
+        let mut args = WithCommonArgs::new(ArgPathfinder::new());
+        args.output.set_save_cmdstan_config(true);
+        assert!(args.check_version(&CmdStanVersion::new(2, 36, 0)).is_ok());
+        match args.check_version(&CmdStanVersion::new(2, 30, 0)) {
+            Err(ArgError::Unsupported(_, missing)) => assert_eq!(missing, [
+                ("pathfinder".to_string(), CmdStanVersion::new(2, 33, 0)),
+                ("output save_cmdstan_config".to_string(), CmdStanVersion::new(2, 33, 0)),
+            ]),
+            r => panic!("expected Unsupported, found {r:?}"),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use super::arg_node::ArgNode;
+use super::arg_parse::ARG_SCHEMA;
+use crate::error::ArgError;
+use serde_json::{Map, Value};
+
+impl ArgNode {
+    /// Convert to the layout of CmdStan's `output_config.json`.
+    ///
+    /// A group is an object, a value is a JSON value, and a choice is its value plus a sibling
+    /// object named after the value (`"algorithm": "hmc", "hmc": {...}`).
+    /// With `omit_defaults`, values and groups equal to their default are left out; choices are always kept.
+    pub fn to_json(&self, omit_defaults: bool) -> Value {
+        let mut res = Map::new();
+        for child in &self.children {
+            match (&child.value, child.children.first()) {
+                (Some(value), group) => {
+                    let is_choice = group.is_some_and(|g| g.name == *value);
+                    if omit_defaults && child.is_default && !is_choice {
+                        continue;
+                    }
+                    res.insert(child.name.clone(), json_scalar(value));
+                    if let Some(group) = group.filter(|g| is_choice && !(omit_defaults && g.is_default)) {
+                        res.insert(group.name.clone(), group.to_json(omit_defaults));
+                    }
+                }
+                (None, _) => {
+                    if !(omit_defaults && child.is_default) {
+                        res.insert(child.name.clone(), child.to_json(omit_defaults));
+                    }
+                }
+            }
+        }
+        Value::Object(res)
+    }
+
+    /// Read the layout of `to_json`, which is the layout of CmdStan's `output_config.json`.
+    ///
+    /// return the root node. Keys CmdStan adds (`stan_major_version`, `model_name`, ...) are kept as values.
+    pub fn from_json(value: &Value) -> Result<ArgNode, ArgError> {
+        object_to_node("", value)
+    }
+}
+
+/// a value as CmdStan writes it: booleans and numbers unquoted
+fn json_scalar(value: &str) -> Value {
+    match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => value.parse::<i64>().map(Value::from)
+            .ok()
+            .or_else(|| value.parse::<f64>().ok().and_then(serde_json::Number::from_f64).map(Value::Number))
+            .unwrap_or_else(|| Value::String(value.to_string())),
+    }
+}
+
+fn object_to_node(name: &str, value: &Value) -> Result<ArgNode, ArgError> {
+    let Value::Object(map) = value else {
+        return Err(ArgError::BadConfig(format!("expected an object for {}", if name.is_empty() { "the root" } else { name })));
+    };
+    // the objects which are the group of a choice, they're put under the choice
+    let chosen: Vec<&str> = map.values().filter_map(|v| v.as_str()).filter(|v| map.get(*v).is_some_and(Value::is_object)).collect();
+
+    let mut res = ArgNode::new(name);
+    for (key, v) in map {
+        let node = match v {
+            Value::Object(_) if chosen.contains(&key.as_str()) => continue,
+            Value::Object(_) => object_to_node(key, v)?,
+            Value::Null => continue,
+            Value::String(s) => {
+                let mut node = ArgNode::with_value(key, s);
+                if let Some(group) = map.get(s).filter(|g| g.is_object()) {
+                    node.children.push(object_to_node(s, group)?);
+                }
+                node
+            }
+            Value::Bool(_) | Value::Number(_) => ArgNode::with_value(key, &v.to_string()),
+            Value::Array(_) => return Err(ArgError::BadConfig(format!("unexpected array for {key}"))),
+        };
+        res.children.push(node);
+    }
+    Ok(res)
+}
+
+/// The node to save a tree as, in the root layout: a method tree is put under `method`, any other named tree under the root.
+pub(crate) fn config_root(node: ArgNode) -> ArgNode {
+    if node.name.is_empty() {
+        return node;
+    }
+    let methods = ARG_SCHEMA.children().iter().find(|k| k.name() == "method").map(|k| k.children()).unwrap_or_default();
+    if methods.iter().any(|m| m.name() == node.name) {
+        let name = node.name.clone();
+        ArgNode::group("", vec![ArgNode::choice("method", &name, "sample", Some(node))])
+    } else {
+        ArgNode::group("", vec![node])
+    }
+}
+
+pub(crate) fn to_toml_string(json: &Value) -> Result<String, ArgError> {
+    let value = toml::Value::try_from(json).map_err(|e| ArgError::BadConfig(e.to_string()))?;
+    toml::to_string_pretty(&value).map_err(|e| ArgError::BadConfig(e.to_string()))
+}
+
+pub(crate) fn parse_toml(text: &str) -> Result<Value, ArgError> {
+    let value: toml::Value = toml::from_str(text).map_err(|e| ArgError::BadConfig(e.to_string()))?;
+    serde_json::to_value(value).map_err(|e| ArgError::BadConfig(e.to_string()))
+}
+
+pub(crate) fn parse_json(text: &str) -> Result<Value, ArgError> {
+    serde_json::from_str(text).map_err(|e| ArgError::BadConfig(e.to_string()))
+}
+
+#[cfg(test)]
+mod arg_config_test {
+    use super::*;
+    use crate::stan_command::{FromArgNode, ToArgNode};
+    use crate::stan_command::common_arg::WithCommonArgs;
+    use crate::stan_command::sample::{ArgSample, ArgSampleEngine};
+    use crate::stan_command::pathfinder::ArgPathfinder;
+    use crate::stan_command::arg_tree::*;
+
+    fn sample_args() -> WithCommonArgs<ArgSample> {
+        let mut args = WithCommonArgs::new(ArgSample::new().with_num_samples(500).unwrap());
+        args.root.algorithm.get_mut_hmc().unwrap().set_engine(ArgSampleEngine::Static(2.0)).set_stepsize(0.5).unwrap();
+        args.data.set_file(ArgReadablePath::Borrowed("data.json"));
+        args.random.set_seed(Some(42));
+        args
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let args = sample_args();
+        for omit_defaults in [false, true] {
+            let json = args.to_json_string(omit_defaults);
+            assert_eq!(WithCommonArgs::<ArgSample>::from_json_str(&json).unwrap(), args);
+            let toml = args.to_toml_string(omit_defaults).unwrap();
+            assert_eq!(WithCommonArgs::<ArgSample>::from_toml_str(&toml).unwrap(), args);
+        }
+
+        let json: Value = serde_json::from_str(&args.to_json_string(true)).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "method": "sample",
+            "sample": {
+                "num_samples": 500,
+                "algorithm": "hmc",
+                "hmc": {"engine": "static", "static": {"int_time": 2}, "stepsize": 0.5},
+            },
+            "data": {"file": "data.json"},
+            "random": {"seed": 42},
+        }));
+
+        let pathfinder = ArgPathfinder::new().with_num_paths(2).unwrap();
+        assert_eq!(ArgPathfinder::from_json_str(&pathfinder.to_json_string(true)).unwrap(), pathfinder);
+    }
+
+    fn assert_round_trip<T: ToArgNode+FromArgNode+PartialEq+std::fmt::Debug>(tree: &T) {
+        for omit_defaults in [false, true] {
+            assert_eq!(&T::from_json_str(&tree.to_json_string(omit_defaults)).unwrap(), tree, "{omit_defaults}");
+            assert_eq!(&T::from_toml_str(&tree.to_toml_string(omit_defaults).unwrap()).unwrap(), tree, "{omit_defaults}");
+        }
+    }
+
+    #[test]
+    fn test_subtree_round_trip() {
+        use crate::stan_command::common_arg::*;
+        use crate::stan_command::sample::*;
+        use crate::stan_command::optimize::*;
+        use crate::stan_command::variational::*;
+        use crate::stan_command::diagnose::ArgDiagnoseGradient;
+
+        assert_round_trip(&ArgID::new().with_id(3));
+        assert_round_trip(&ArgData::new().with_file(ArgReadablePath::Borrowed("data.json")));
+        assert_round_trip(&ArgRandom::new().with_seed(Some(7)));
+        assert_round_trip(&ArgNumThreads::new().with_threads(4));
+        assert_round_trip(&ArgOutput::new().with_file(ArgWritablePath::Borrowed("fit.csv")).with_refresh(10));
+        assert_round_trip(&ArgSampleAdapt::new().with_delta(0.95).unwrap());
+        assert_round_trip(&ArgSampleAlgorithm::FixedParam);
+        assert_round_trip(&ArgSampleEngine::Static(2.0));
+        assert_round_trip(&ArgSampleMetric::DenseE);
+        assert_round_trip(&ArgOptimizeAlgorithm::Newton);
+        assert_round_trip(&ArgOptimizeAlgorithm::LBfgs(ArgOptimizeBfgs::ARG_DEFAULT, 7));
+        assert_round_trip(&ArgOptimizeBfgs::ARG_DEFAULT);
+        assert_round_trip(&ArgVariationalAlgorithm::Fullrank);
+        assert_round_trip(&ArgVariationalAdapt { engaged: false, iter: 20 });
+        assert_round_trip(&ArgDiagnoseGradient { epsilon: 1e-4, error: 1e-5 });
+        // every default tree, which may be saved as nothing at all
+        assert_round_trip(&ArgID::new());
+        assert_round_trip(&ArgOutput::new());
+        assert_round_trip(&ArgVariationalAlgorithm::new());
+        assert_round_trip(&ArgInit::new());
+
+        // inits given as values are saved as their file
+        assert_round_trip(&ArgInit::Range(0.5));
+        assert_round_trip(&ArgInit::Path(ArgReadablePath::Borrowed("inits.json")));
+        let mut init = ArgInit::new();
+        init.set_init_by_param("mu", 0.5);
+        assert_eq!(ArgInit::from_json_str(&init.to_json_string(true)).unwrap(), ArgInit::Path(ArgReadablePath::Borrowed("init.json")));
+    }
+
+    #[test]
+    fn test_cmdstan_config() {
+        let text = r#"{
+            "stan_major_version" : "2",
+            "model_name" : "bernoulli_model",
+            "method" : "sample",
+            "sample" : {
+                "num_samples" : 500, "num_warmup" : 1000, "save_warmup" : false, "thin" : 1,
+                "adapt" : {"engaged" : true, "delta" : 0.95},
+                "algorithm" : "hmc",
+                "hmc" : {"engine" : "nuts", "nuts" : {"max_depth" : 12}, "metric" : "dense_e", "metric_file" : "", "stepsize" : 1},
+                "num_chains" : 1
+            },
+            "id" : 2,
+            "data" : {"file" : "bernoulli.data.json"},
+            "init" : "2",
+            "random" : {"seed" : 7},
+            "output" : {"file" : "output.csv", "diagnostic_file" : "", "refresh" : 100, "sig_figs" : -1},
+            "num_threads" : 1,
+            "mpi_enabled" : false
+        }"#;
+        let args = WithCommonArgs::<ArgSample>::from_json_str(text).unwrap();
+        assert_eq!(args.root.num_samples, 500);
+        assert_eq!(args.root.adapt.delta, 0.95);
+        let hmc = args.root.algorithm.expect_hmc();
+        assert_eq!(hmc.engine, ArgSampleEngine::Nuts(12));
+        assert_eq!(hmc.metric, crate::stan_command::sample::ArgSampleMetric::DenseE);
+        assert_eq!((args.id.id, args.random.seed), (2, Some(7)));
+        assert!(args.output.is_default());
+    }
+}
@@ -0,0 +1,409 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::io::{BufRead, BufReader};
+use crate::arg_paths::{ArgReadablePath, ArgWritablePath};
+use crate::error::ArgError;
+use super::WithDefaultArg;
+use super::arg_config;
+
+/// A node of the CmdStan configuration tree.
+///
+/// This is the shape CmdStan prints at the top of every output CSV:
+///
+/// ```text
+/// # method = sample (Default)
+/// #   sample
+/// #     num_samples = 1000 (Default)
+/// #     adapt
+/// #       engaged = true (Default)
+/// ```
+///
+/// A keyword with a value (`num_samples = 1000`) has `value`, a group (`adapt`) has children;
+/// a choice (`method = sample`) has both a value and a child group named after the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgNode {
+    pub name: String,
+    pub value: Option<String>,
+    /// CmdStan marked the value as `(Default)`
+    pub is_default: bool,
+    pub children: Vec<ArgNode>,
+}
+
+impl ArgNode {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), value: None, is_default: false, children: Vec::new() }
+    }
+
+    pub fn with_value(name: &str, value: &str) -> Self {
+        Self { value: Some(value.to_string()), ..Self::new(name) }
+    }
+
+    /// a value node, marked as default when `value == default`
+    pub fn value<V: Display+PartialEq>(name: &str, value: &V, default: &V) -> Self {
+        Self { is_default: value == default, ..Self::with_value(name, &value.to_string()) }
+    }
+
+    /// a group node, marked as default when all of its children are
+    pub fn group(name: &str, children: impl Into<Vec<ArgNode>>) -> Self {
+        let children = children.into();
+        Self { is_default: children.iter().all(|c| c.is_default), children, ..Self::new(name) }
+    }
+
+    /// a choice node (`algorithm = hmc`) with the group of the chosen value, if it has one
+    pub fn choice(name: &str, value: &str, default_value: &str, group: Option<ArgNode>) -> Self {
+        Self {
+            is_default: value == default_value && group.as_ref().is_none_or(|g| g.is_default),
+            children: group.into_iter().collect(),
+            ..Self::with_value(name, value)
+        }
+    }
+
+    pub fn child(&self, name: &str) -> Option<&ArgNode> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    pub fn child_mut(&mut self, name: &str) -> Option<&mut ArgNode> {
+        self.children.iter_mut().find(|c| c.name == name)
+    }
+
+    /// the value of the child `name`, if there's one
+    pub fn value_of(&self, name: &str) -> Option<&str> {
+        self.child(name).and_then(|c| c.value.as_deref())
+    }
+
+    /// the child `name`, or an error naming the missing keyword
+    pub fn expect_child(&self, name: &str) -> Result<&ArgNode, ArgError> {
+        self.child(name).ok_or_else(|| ArgError::NotValidArgTreeType(
+            format!("expected keyword {name} under {}", self.display_name())))
+    }
+
+    /// check that this node is the argument `name`
+    pub fn expect_name(&self, name: &str) -> Result<&Self, ArgError> {
+        if self.name == name {
+            Ok(self)
+        } else {
+            Err(ArgError::NotValidArgTreeType(format!("expected argument {name}, found {}", self.display_name())))
+        }
+    }
+
+    /// for a choice node (`algorithm = hmc`), return the chosen value and its group node (`hmc`), if any
+    pub fn chosen(&self) -> Option<(&str, Option<&ArgNode>)> {
+        let value = self.value.as_deref()?;
+        Some((value, self.child(value)))
+    }
+
+    /// the group of the chosen method, under the root node
+    pub fn method_group(&self) -> Result<ArgNode, ArgError> {
+        match self.expect_child("method")?.chosen() {
+            Some((_, Some(group))) => Ok(group.clone()),
+            Some((name, None)) => Ok(ArgNode::new(name)),
+            None => Err(ArgError::NotValidArgTreeType("method has no value".to_string())),
+        }
+    }
+
+    fn display_name(&self) -> &str {
+        if self.name.is_empty() { "the root" } else { &self.name }
+    }
+
+    /// Set `target` from the value of the child `name`.
+    ///
+    /// A missing child, or one marked `(Default)`, leaves `target` unchanged.
+    pub fn read_value<V: FromArgValue>(&self, name: &str, target: &mut V) -> Result<(), ArgError> {
+        let Some(child) = self.child(name) else {
+            return Ok(());
+        };
+        if child.is_default {
+            return Ok(());
+        }
+        let raw = child.value.as_deref().unwrap_or_default();
+        *target = V::from_arg_value(raw).ok_or_else(|| ArgError::BadArgumentValue(
+            format!("{}: cannot parse {name} = {raw}", self.display_name())))?;
+        Ok(())
+    }
+
+    /// Set `target` from the value of this node, e.g. `id = 2`; no value, or one marked `(Default)`, leaves it unchanged.
+    pub fn read_own_value<V: FromArgValue>(&self, target: &mut V) -> Result<(), ArgError> {
+        let Some(raw) = self.value.as_deref().filter(|_| !self.is_default) else {
+            return Ok(());
+        };
+        *target = V::from_arg_value(raw).ok_or_else(|| ArgError::BadArgumentValue(
+            format!("cannot parse {} = {raw}", self.display_name())))?;
+        Ok(())
+    }
+
+    /// Parse the configuration comments at the top of a Stan CSV file.
+    ///
+    /// return the root node, whose children are `method`, `id`, `data`, `output`, etc.
+    pub fn parse_csv_header(text: &str) -> Result<ArgNode, ArgError> {
+        let mut root = ArgNode::new("");
+        // the path of indices from the root to the last node of each indent level
+        let mut stack: Vec<usize> = Vec::new();
+
+        for line in text.lines() {
+            let Some(comment) = line.strip_prefix('#') else {
+                break; // the column header ends the configuration
+            };
+            let comment = comment.strip_prefix(' ').unwrap_or(comment);
+            let content = comment.trim();
+            if content.is_empty() {
+                continue;
+            }
+            if content.contains(':') && !content.contains('=') {
+                break; // "Adaptation terminated" etc. never appear before the columns, but stop anyway
+            }
+            let indent = (comment.len() - comment.trim_start().len()) / 2;
+            if indent > stack.len() {
+                return Err(ArgError::NotValidArgTreeType(format!("bad indentation in CSV header line: {line}")));
+            }
+
+            let mut node = match content.split_once('=') {
+                Some((name, value)) => {
+                    let value = value.trim();
+                    let (value, is_default) = match value.strip_suffix("(Default)") {
+                        Some(v) => (v.trim(), true),
+                        None => (value, false),
+                    };
+                    ArgNode { is_default, ..ArgNode::with_value(name.trim(), value) }
+                }
+                None => ArgNode::new(content),
+            };
+            node.children.clear();
+
+            stack.truncate(indent);
+            let mut parent = &mut root;
+            for i in &stack {
+                parent = &mut parent.children[*i];
+            }
+            parent.children.push(node);
+            stack.push(parent.children.len() - 1);
+        }
+        Ok(root)
+    }
+
+    /// read the configuration header of a Stan CSV file, see `parse_csv_header`
+    pub fn read_csv_header(path: &Path) -> Result<ArgNode, ArgError> {
+        let file = std::fs::File::open(path).map_err(ArgError::FileSystemError)?;
+        let mut header = String::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(ArgError::FileSystemError)?;
+            if !line.starts_with('#') {
+                break;
+            }
+            header.push_str(&line);
+            header.push('\n');
+        }
+        Self::parse_csv_header(&header)
+    }
+}
+
+/// The tree view: one keyword per line, indented like the CSV header.
+///
+/// Lines marked with `*` differ from the default, or contain a value which does.
+impl Display for ArgNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn write_node(node: &ArgNode, depth: usize, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let mark = if node.is_default { ' ' } else { '*' };
+            write!(f, "{mark} {:indent$}{}", "", node.name, indent = depth * 2)?;
+            if let Some(v) = &node.value {
+                write!(f, " = {v}")?;
+            }
+            writeln!(f)?;
+            node.children.iter().try_for_each(|c| write_node(c, depth + 1, f))
+        }
+
+        if self.name.is_empty() {
+            self.children.iter().try_for_each(|c| write_node(c, 0, f))
+        } else {
+            write_node(self, 0, f)
+        }
+    }
+}
+
+/// An argument tree which can be turned into an `ArgNode`, marking the values equal to `ARG_DEFAULT`.
+///
+/// Inits given as values, `ArgInit::ParamValue` and `ArgInit::PerChain`, are saved as the name of their file,
+/// which is what CmdStan receives, and load back as `ArgInit::Path` of that file: the values aren't saved.
+pub trait ToArgNode {
+    /// the node of this argument, e.g. the `sample` group for `ArgSample`,
+    /// or the root node for `WithCommonArgs`
+    fn to_arg_node(&self) -> ArgNode;
+
+    /// Save as pretty JSON in the layout of CmdStan's `output_config.json`, see `ArgNode::to_json`.
+    ///
+    /// A method tree is saved under `method`, e.g. `{"method": "sample", "sample": {...}}`.
+    fn to_json_string(&self, omit_defaults: bool) -> String {
+        let json = arg_config::config_root(self.to_arg_node()).to_json(omit_defaults);
+        serde_json::to_string_pretty(&json).unwrap_or_default()
+    }
+
+    /// Save as TOML, with the same layout as `to_json_string`.
+    fn to_toml_string(&self, omit_defaults: bool) -> Result<String, ArgError> {
+        arg_config::to_toml_string(&arg_config::config_root(self.to_arg_node()).to_json(omit_defaults))
+    }
+
+    /// write a config file, as TOML if its extension is `.toml`, else as JSON
+    fn save_config(&self, path: &Path, omit_defaults: bool) -> Result<(), ArgError> {
+        let text = if path.extension().is_some_and(|e| e == "toml") {
+            self.to_toml_string(omit_defaults)?
+        } else {
+            self.to_json_string(omit_defaults)
+        };
+        std::fs::write(path, text).map_err(ArgError::FileSystemError)
+    }
+}
+
+/// An argument tree which can be rebuilt from its `ArgNode`.
+pub trait FromArgNode: Sized {
+    /// build from the node of this argument, e.g. the `sample` group for `ArgSample`,
+    /// or the root node for `WithCommonArgs`
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError>;
+
+    /// the method of a method tree, e.g. `sample` for `ArgSample`
+    const METHOD: Option<&'static str> = None;
+
+    /// the name of the node of any other tree but the root, e.g. `adapt` for `ArgSampleAdapt`
+    const NAME: Option<&'static str> = None;
+
+    /// Parse a CmdStan command line, such as `./model sample num_samples=500 data file=d.json`.
+    ///
+    /// A method tree only reads its method group, see `ArgNode::parse_arg_string`.
+    fn from_arg_string(line: &str) -> Result<Self, ArgError> {
+        Self::from_root_node(&ArgNode::parse_arg_string(line)?)
+    }
+
+    /// Build from a root node, taking the method group for a method tree, or the node `NAME` for a subtree.
+    ///
+    /// A subtree missing from the root, as saved without its defaults, is built from an empty node.
+    fn from_root_node(root: &ArgNode) -> Result<Self, ArgError> {
+        match (Self::METHOD, Self::NAME) {
+            (Some(_), _) => Self::from_arg_node(&root.method_group()?),
+            (None, Some(name)) if root.name != name => match root.child(name) {
+                Some(node) => Self::from_arg_node(node),
+                None => Self::from_arg_node(&ArgNode::new(name)),
+            },
+            (None, _) => Self::from_arg_node(root),
+        }
+    }
+
+    /// read a config in the layout of `ToArgNode::to_json_string`, or CmdStan's `output_config.json`
+    fn from_json_str(text: &str) -> Result<Self, ArgError> {
+        Self::from_root_node(&ArgNode::from_json(&arg_config::parse_json(text)?)?)
+    }
+
+    /// read a config in the layout of `ToArgNode::to_toml_string`
+    fn from_toml_str(text: &str) -> Result<Self, ArgError> {
+        Self::from_root_node(&ArgNode::from_json(&arg_config::parse_toml(text)?)?)
+    }
+
+    /// read a config file, as TOML if its extension is `.toml`, else as JSON
+    fn load_config(path: &Path) -> Result<Self, ArgError> {
+        let text = std::fs::read_to_string(path).map_err(ArgError::FileSystemError)?;
+        if path.extension().is_some_and(|e| e == "toml") {
+            Self::from_toml_str(&text)
+        } else {
+            Self::from_json_str(&text)
+        }
+    }
+
+    /// rebuild the arguments of the run which wrote a Stan CSV file
+    fn from_csv_header(path: &Path) -> Result<Self, ArgError> {
+        Self::from_arg_node(&ArgNode::read_csv_header(path)?)
+    }
+}
+
+/// A single argument value as CmdStan prints it.
+pub trait FromArgValue: Sized {
+    fn from_arg_value(s: &str) -> Option<Self>;
+}
+
+macro_rules! impl_from_arg_value_by_parse {
+    ($($t:ty),+) => {
+        $(impl FromArgValue for $t {
+            fn from_arg_value(s: &str) -> Option<Self> {
+                s.trim().parse().ok()
+            }
+        })+
+    };
+}
+impl_from_arg_value_by_parse!{u32, i32, f64}
+
+impl FromArgValue for bool {
+    fn from_arg_value(s: &str) -> Option<Self> {
+        match s.trim() {
+            "true" | "1" => Some(true),
+            "false" | "0" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl FromArgValue for Option<u32> {
+    fn from_arg_value(s: &str) -> Option<Self> {
+        match s.trim() {
+            "" => Some(None),
+            s => s.parse().ok().map(Some),
+        }
+    }
+}
+
+impl FromArgValue for ArgReadablePath {
+    fn from_arg_value(s: &str) -> Option<Self> {
+        match s.trim() {
+            "" => Some(Self::ARG_DEFAULT),
+            s => Some(Self::Owned(PathBuf::from(s))),
+        }
+    }
+}
+
+impl FromArgValue for ArgWritablePath {
+    fn from_arg_value(s: &str) -> Option<Self> {
+        match s.trim() {
+            "" => Some(Self::ARG_DEFAULT),
+            s => Some(Self::Owned(PathBuf::from(s))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod arg_node_test {
+    use super::*;
+    use crate::stan_command::common_arg::WithCommonArgs;
+    use crate::stan_command::sample::ArgSample;
+    use crate::stan_command::optimize::{ArgOptimize, ArgOptimizeAlgorithm, ArgOptimizeBfgs};
+
+    #[test]
+    fn test_sample_from_csv_header() {
+        let args = WithCommonArgs::<ArgSample>::from_csv_header(Path::new("examples/bernoulli/output.csv")).unwrap();
+        let mut expected = WithCommonArgs::new(ArgSample::new());
+        expected.data.set_file(ArgReadablePath::Owned(r"examples\bernoulli\bernoulli.data.json".into()));
+        expected.output.file = ArgWritablePath::Owned(r"examples\bernoulli\output.csv".into());
+        expected.random.set_seed(Some(1221890664));
+        assert_eq!(args, expected);
+    }
+
+    #[test]
+    fn test_optimize_from_header() {
+        let header = "\
+# model = m_model
+# method = optimize
+#   optimize
+#     algorithm = lbfgs (Default)
+#       lbfgs
+#         init_alpha = 0.01
+#         tol_obj = 1e-12 (Default)
+#         history_size = 7
+#     jacobian = true
+#     iter = 2000 (Default)
+# id = 3
+# init = 0.5
+lp__,mu
+";
+        let node = ArgNode::parse_csv_header(header).unwrap();
+        assert_eq!(node.child("method").and_then(|m| m.chosen()).map(|c| c.0), Some("optimize"));
+        let args = WithCommonArgs::<ArgOptimize>::from_arg_node(&node).unwrap();
+        let bfgs = ArgOptimizeBfgs::new().with_init_alpha(0.01).unwrap();
+        assert_eq!(args.root, ArgOptimize::new().with_algorithm(ArgOptimizeAlgorithm::LBfgs(bfgs, 7)).with_jacobian(true));
+        assert_eq!(args.id.id, 3);
+        assert!(WithCommonArgs::<ArgSample>::from_arg_node(&node).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use super::arg_node::ArgNode;
+use crate::error::ArgError;
+use ArgSchema::{Value as V, Group as G, Choice as C};
+
+/// A keyword of the CmdStan command line.
+#[derive(Debug)]
+pub(crate) enum ArgSchema {
+    /// `name=value`
+    Value(&'static str),
+    /// `name`, followed by the keywords of the group
+    Group(&'static str, &'static [ArgSchema]),
+    /// `name=option` (or the bare `option`), followed by the keywords of the option, each option is a group
+    Choice(&'static str, &'static [ArgSchema]),
+}
+
+impl ArgSchema {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            V(name) | G(name, _) | C(name, _) => name,
+        }
+    }
+
+    /// the keywords of a group, or the options of a choice
+    pub(crate) fn children(&self) -> &'static [ArgSchema] {
+        match self {
+            V(_) => &[],
+            G(_, c) | C(_, c) => c,
+        }
+    }
+}
+
+const SAMPLE: ArgSchema = G("sample", &[
+    V("num_samples"), V("num_warmup"), V("save_warmup"), V("thin"),
+    G("adapt", &[
+        V("engaged"), V("gamma"), V("delta"), V("kappa"), V("t0"),
+        V("init_buffer"), V("term_buffer"), V("window"), V("save_metric"),
+    ]),
+    C("algorithm", &[
+        G("hmc", &[
+            C("engine", &[G("nuts", &[V("max_depth")]), G("static", &[V("int_time")])]),
+            V("metric"), V("metric_file"), V("stepsize"), V("stepsize_jitter"),
+        ]),
+        G("fixed_param", &[]),
+    ]),
+    V("num_chains"),
+]);
+
+const OPTIMIZE: ArgSchema = G("optimize", &[
+    C("algorithm", &[
+        G("bfgs", &[V("init_alpha"), V("tol_obj"), V("tol_rel_obj"), V("tol_grad"), V("tol_rel_grad"), V("tol_param")]),
+        G("lbfgs", &[V("init_alpha"), V("tol_obj"), V("tol_rel_obj"), V("tol_grad"), V("tol_rel_grad"), V("tol_param"), V("history_size")]),
+        G("newton", &[]),
+    ]),
+    V("jacobian"), V("iter"), V("save_iterations"),
+]);
+
+const VARIATIONAL: ArgSchema = G("variational", &[
+    C("algorithm", &[G("meanfield", &[]), G("fullrank", &[])]),
+    V("iter"), V("grad_samples"), V("elbo_samples"), V("eta"),
+    G("adapt", &[V("engaged"), V("iter")]),
+    V("tol_rel_obj"), V("eval_elbo"), V("output_samples"),
+]);
+
+const DIAGNOSE: ArgSchema = G("diagnose", &[
+    C("test", &[G("gradient", &[V("epsilon"), V("error")])]),
+]);
+
+const GENERATE: ArgSchema = G("generate_quantities", &[V("fitted_params"), V("num_chains")]);
+
+const PATHFINDER: ArgSchema = G("pathfinder", &[
+    V("init_alpha"), V("tol_obj"), V("tol_rel_obj"), V("tol_grad"), V("tol_rel_grad"), V("tol_param"),
+    V("history_size"), V("num_psis_draws"), V("num_paths"), V("save_single_paths"), V("psis_resample"),
+    V("calculate_lp"), V("max_lbfgs_iters"), V("num_draws"), V("num_elbo_draws"),
+]);
+
+const LOG_PROB: ArgSchema = G("log_prob", &[V("unconstrained_params"), V("constrained_params"), V("jacobian")]);
+
+const LAPLACE: ArgSchema = G("laplace", &[V("mode"), V("jacobian"), V("draws"), V("calculate_lp")]);
+
+/// the whole CmdStan argument hierarchy, the root group has no name
+pub(crate) const ARG_SCHEMA: ArgSchema = G("", &[
+    C("method", &[SAMPLE, OPTIMIZE, VARIATIONAL, DIAGNOSE, GENERATE, PATHFINDER, LOG_PROB, LAPLACE]),
+    V("id"),
+    G("data", &[V("file")]),
+    V("init"),
+    G("random", &[V("seed")]),
+    G("output", &[
+        V("file"), V("diagnostic_file"), V("refresh"), V("sig_figs"), V("profile_file"), V("save_cmdstan_config"),
+    ]),
+    V("num_threads"),
+]);
+
+/// the keywords in scope and the path of their node from the root
+struct Context {
+    keywords: &'static [ArgSchema],
+    path: Vec<usize>,
+}
+
+fn node_at<'a>(root: &'a mut ArgNode, path: &[usize]) -> &'a mut ArgNode {
+    path.iter().fold(root, |node, i| &mut node.children[*i])
+}
+
+/// Find `key` in the innermost context having it.
+///
+/// return the depth of the context, the keyword, and for a choice the option named by the value or by `key` itself.
+fn lookup(stack: &[Context], key: &str, value: Option<&str>) -> Option<(usize, &'static ArgSchema, Option<&'static ArgSchema>)> {
+    for (depth, context) in stack.iter().enumerate().rev() {
+        for keyword in context.keywords {
+            if keyword.name() == key {
+                let option = value.and_then(|v| keyword.children().iter().find(|o| o.name() == v));
+                return Some((depth, keyword, option));
+            }
+            if let C(_, options) = keyword
+                && value.is_none()
+                && let Some(option) = options.iter().find(|o| o.name() == key) {
+                return Some((depth, keyword, Some(option)));
+            }
+        }
+    }
+    None
+}
+
+/// the places `key` is valid at, written as the keywords leading there
+fn find_paths(schema: &'static ArgSchema, key: &str, prefix: &str, res: &mut Vec<String>) {
+    for child in schema.children() {
+        if child.name() == key {
+            res.push(if prefix.is_empty() { "the top level".to_string() } else { prefix.to_string() });
+        }
+        let child_prefix = |name: String| if prefix.is_empty() { name } else { format!("{prefix} {name}") };
+        match child {
+            V(_) => {}
+            G(name, _) => find_paths(child, key, &child_prefix(name.to_string()), res),
+            C(name, options) => {
+                for option in *options {
+                    find_paths(option, key, &child_prefix(format!("{name}={}", option.name())), res);
+                }
+            }
+        }
+    }
+}
+
+fn unknown_keyword(key: &str) -> String {
+    let mut paths = Vec::new();
+    find_paths(&ARG_SCHEMA, key, "", &mut paths);
+    if paths.is_empty() {
+        format!("unknown keyword {key}")
+    } else {
+        format!("{key} is not valid here, it belongs under {}", paths.join(" or "))
+    }
+}
+
+/// split a shell command line into tokens, honoring quotes and `\` line continuations
+fn split_arg_string(line: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut token: Option<String> = None;
+    let mut quote = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => token.get_or_insert_default().push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                token.get_or_insert_default();
+            }
+            (None, '\\') if chars.peek().is_none_or(|n| n.is_whitespace()) => {
+                // a line continuation
+            }
+            (None, c) if c.is_whitespace() => res.extend(token.take()),
+            (None, c) => token.get_or_insert_default().push(c),
+        }
+    }
+    res.extend(token);
+    res
+}
+
+impl ArgNode {
+    /// Parse CmdStan arguments, such as `["sample", "num_samples=500", "adapt", "delta=0.95"]`.
+    ///
+    /// A keyword is looked up in the group opened last, then in the groups enclosing it,
+    /// the same as CmdStan does. return the root node, in the layout of `parse_csv_header`.
+    ///
+    /// # Errors
+    ///
+    /// ArgError::BadArgString for an unknown or misplaced keyword, a missing or unexpected value,
+    /// or a keyword given twice in the same group.
+    pub fn parse_args<S: AsRef<str>>(args: &[S]) -> Result<ArgNode, ArgError> {
+        parse_tokens(args.iter().map(|a| a.as_ref()).enumerate())
+    }
+
+    /// Parse a CmdStan command line, such as `./model sample num_samples=500 data file=d.json`.
+    ///
+    /// The leading model path is optional. see `parse_args`
+    pub fn parse_arg_string(line: &str) -> Result<ArgNode, ArgError> {
+        let tokens = split_arg_string(line);
+        let root = [Context { keywords: ARG_SCHEMA.children(), path: Vec::new() }];
+        let skip = tokens.first().is_some_and(|t| !t.contains('=') && lookup(&root, t, None).is_none());
+        parse_tokens(tokens.iter().map(String::as_str).enumerate().skip(skip as usize))
+    }
+}
+
+fn parse_tokens<'a>(tokens: impl Iterator<Item = (usize, &'a str)>) -> Result<ArgNode, ArgError> {
+    let mut root = ArgNode::new("");
+    let mut stack = vec![Context { keywords: ARG_SCHEMA.children(), path: Vec::new() }];
+
+    for (i, arg) in tokens {
+        let err = |msg: String| ArgError::BadArgString(i, arg.to_string(), msg);
+        let (key, value) = match arg.split_once('=') {
+            Some((k, v)) => (k, Some(v)),
+            None => (arg, None),
+        };
+        let (depth, keyword, option) = lookup(&stack, key, value).ok_or_else(|| err(unknown_keyword(key)))?;
+        stack.truncate(depth + 1);
+        let path = stack[depth].path.clone();
+        let parent = node_at(&mut root, &path);
+        let duplicate = || err(format!("{} is given twice", keyword.name()));
+
+        match keyword {
+            V(name) => {
+                let value = value.ok_or_else(|| err(format!("{name} expects a value")))?;
+                if parent.child(name).is_some() {
+                    return Err(duplicate());
+                }
+                parent.children.push(ArgNode::with_value(name, value));
+            }
+            G(name, keywords) => {
+                if value.is_some() {
+                    return Err(err(format!("{name} doesn't take a value")));
+                }
+                let index = match parent.children.iter().position(|c| c.name == *name) {
+                    Some(index) => index,
+                    None => {
+                        parent.children.push(ArgNode::new(name));
+                        parent.children.len() - 1
+                    }
+                };
+                stack.push(Context { keywords, path: [path, vec![index]].concat() });
+            }
+            C(name, options) => {
+                let option = option.ok_or_else(|| {
+                    let names: Vec<_> = options.iter().map(|o| o.name()).collect();
+                    err(format!("{name} expects one of {}", names.join(", ")))
+                })?;
+                if parent.child(name).is_some() {
+                    return Err(duplicate());
+                }
+                let mut node = ArgNode::with_value(name, option.name());
+                node.children.push(ArgNode::new(option.name()));
+                parent.children.push(node);
+                stack.push(Context { keywords: option.children(), path: [path, vec![parent.children.len() - 1, 0]].concat() });
+            }
+        }
+    }
+    Ok(root)
+}
+
+#[cfg(test)]
+mod arg_parse_test {
+    use super::*;
+    use crate::stan_command::FromArgNode;
+    use crate::stan_command::common_arg::WithCommonArgs;
+    use crate::stan_command::sample::ArgSample;
+    use crate::stan_command::optimize::{ArgOptimize, ArgOptimizeAlgorithm};
+    use crate::stan_command::arg_tree::*;
+
+    #[test]
+    fn test_parse_sample() {
+        let line = "./model sample num_samples=500 adapt delta=0.95 \\\n  data file=d.json output file='my out.csv'";
+        let args = WithCommonArgs::<ArgSample>::from_arg_string(line).unwrap();
+        let mut expected = WithCommonArgs::new(ArgSample::new().with_num_samples(500).unwrap());
+        expected.root.adapt.set_delta(0.95).unwrap();
+        expected.data.set_file(ArgReadablePath::Owned("d.json".into()));
+        expected.output.file = ArgWritablePath::Owned("my out.csv".into());
+        assert_eq!(args, expected);
+    }
+
+    #[test]
+    fn test_parse_method_only() {
+        let args = ArgOptimize::from_arg_string("method=optimize algorithm=newton iter=10 random seed=3").unwrap();
+        assert_eq!(args, ArgOptimize::new().with_algorithm(ArgOptimizeAlgorithm::Newton).with_iter(10).unwrap());
+        assert!(ArgSample::from_arg_string("optimize").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |s: &str| match ArgNode::parse_arg_string(s) {
+            Err(ArgError::BadArgString(i, _, msg)) => (i, msg),
+            r => panic!("expected an error, found {r:?}"),
+        };
+        assert_eq!(err("./model sample delta=0.9"), (2, "delta is not valid here, it belongs under method=sample adapt".to_string()));
+        assert_eq!(err("sample foo=1"), (1, "unknown keyword foo".to_string()));
+        assert_eq!(err("sample algorithm=nope").1, "algorithm expects one of hmc, fixed_param");
+        assert_eq!(err("sample thin=2 thin=3").1, "thin is given twice");
+    }
+}
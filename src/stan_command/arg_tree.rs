@@ -1,9 +1,10 @@
 use std::{ffi::{OsStr, OsString}, process::Command};
 pub const EPS: f64 = f64::EPSILON * 10.0;
 pub use paste::paste;
-pub use super::{ArgType, WithDefaultArg, ArgThrough};
+pub use super::{ArgType, WithDefaultArg, ArgThrough, CmdStanVersion, ChainFiles};
 pub use crate::arg_paths::{ArgWritablePath, ArgReadablePath, ArgPath};
 pub use crate::error::ArgError;
+pub use super::arg_node::{ArgNode, FromArgNode, FromArgValue, ToArgNode};
 
 pub fn args_combine(name: &str, val: &OsStr) -> OsString {
     let mut res = OsString::new();
@@ -25,6 +26,20 @@ macro_rules! arg_into (
     };
 );
 
+/// set each listed field of `$struct_name` from the child of `$node` with the same name
+macro_rules! arg_from (
+    ($node:ident.{$($member_name:ident),+} >> $struct_name:expr) => {
+        $($node.read_value(stringify!($member_name), &mut $struct_name.$member_name)?;)+
+    };
+);
+
+/// an array of value nodes, one for each listed field of `$struct_name`
+macro_rules! arg_to (
+    ($struct_name:ident.{$($member_name:ident),+} in $struct_type:ty) => {
+        [$(ArgNode::value(stringify!($member_name), &$struct_name.$member_name, &<$struct_type>::ARG_DEFAULT.$member_name)),+]
+    };
+);
+
 macro_rules! ImplDefault {
     ($struct_type:ty) => {
         impl Default for $struct_type {
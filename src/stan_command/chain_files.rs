@@ -0,0 +1,77 @@
+use super::arg_tree::*;
+
+/// The files CmdStan writes for one chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainFiles {
+    /// the chain id, `id + i` for the chain `i` (counting from 0) of a run
+    pub id: u32,
+    pub output: ArgPath,
+    pub diagnostic: Option<ArgPath>,
+    /// the adapted metric, written with `save_metric`
+    pub metric: Option<ArgPath>,
+}
+
+impl ChainFiles {
+    /// Name the files of each chain the way CmdStan does.
+    ///
+    /// With a single chain the files are named as given; with several, each name gets the suffix
+    /// `_<chain id>`, e.g. `output_1.csv`, `output_2.csv`. The metric file is `<output stem>_metric.json`.
+    pub fn plan(output: &ArgWritablePath, diagnostic: &ArgWritablePath, id: u32, num_chains: u32, save_metric: bool) -> Vec<Self> {
+        let name = |file: &ArgWritablePath, chain: u32| match num_chains {
+            1 => file.clone(),
+            _ => file.with_stem_suffix(&chain.to_string()),
+        };
+        (id..id.saturating_add(num_chains.max(1)))
+            .map(|chain| {
+                let output = name(output, chain);
+                Self {
+                    id: chain,
+                    metric: save_metric.then(|| ArgPath::Owned(output.with_stem_suffix("metric").as_path().with_extension("json"))),
+                    diagnostic: (!diagnostic.is_default()).then(|| name(diagnostic, chain).into()),
+                    output: output.into(),
+                }
+            })
+            .collect()
+    }
+
+    /// the output file alone, for a tree without diagnostic or metric files
+    pub fn single(id: u32, output: ArgPath) -> Self {
+        Self { id, output, diagnostic: None, metric: None }
+    }
+
+    /// the output file, then the diagnostic and metric files if any
+    pub fn files(&self) -> impl Iterator<Item = &ArgPath> {
+        [Some(&self.output), self.diagnostic.as_ref(), self.metric.as_ref()].into_iter().flatten()
+    }
+
+    /// check that every file of the chain is readable
+    pub fn verify(&self) -> Result<(), ArgError> {
+        self.files()
+            .try_for_each(|f| f.verify_file_readable())
+            .map_err(ArgError::FileSystemError)
+    }
+}
+
+#[cfg(test)]
+mod chain_files_test {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_plan() {
+        let single = ChainFiles::plan(&ArgWritablePath::Borrowed("out/fit.csv"), &ArgWritablePath::ARG_DEFAULT, 1, 1, true);
+        assert_eq!(single, [ChainFiles {
+            id: 1,
+            output: ArgPath::from("out/fit.csv"),
+            diagnostic: None,
+            metric: Some(ArgPath::from("out/fit_metric.json")),
+        }]);
+
+        let chains = ChainFiles::plan(&ArgWritablePath::Borrowed("out/fit.csv"), &ArgWritablePath::Borrowed("diag.csv"), 3, 2, true);
+        let ids: Vec<_> = chains.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [3, 4]);
+        assert_eq!(chains[1].output.as_path(), Path::new("out/fit_4.csv"));
+        assert_eq!(chains[1].diagnostic.as_ref().map(|d| d.as_path()), Some(Path::new("diag_4.csv")));
+        assert_eq!(chains[1].metric.as_ref().map(|d| d.as_path()), Some(Path::new("out/fit_4_metric.json")));
+    }
+}
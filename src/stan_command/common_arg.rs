@@ -1,7 +1,7 @@
 use super::arg_tree::*;
 use std::process::Command;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WithCommonArgs<T:ArgThrough>  {
     pub root: T,
     pub id: ArgID,
@@ -18,19 +18,54 @@ impl<T:ArgThrough> ArgThrough for WithCommonArgs<T> {
     }
 
     fn arg_through(&self, cmd: &mut Command) -> Result<(), ArgError> {
-        self.root.arg_through(cmd)?;
-        self.id.arg_through(cmd)?;
-        self.data.arg_through(cmd)?;
-        self.init.arg_through(cmd)?;
-        self.random.arg_through(cmd)?;
-        self.output.arg_through(cmd)?;
-        self.num_threads.arg_through(cmd)?;
-        Ok(())
+        self.args_through(cmd, true)
+    }
+
+    /// the init files are named but not written
+    fn arg_through_dry(&self, cmd: &mut Command) -> Result<(), ArgError> {
+        self.args_through(cmd, false)
+    }
+
+    fn get_id(&self) -> u32 {
+        self.id.id
     }
 
     fn get_output_path(&self) -> Result<ArgPath, ArgError> {
         self.output.get_output_path()
     }
+
+    fn collect_problems(&self, problems: &mut Vec<String>) {
+        self.root.collect_problems(problems);
+    }
+
+    fn collect_warnings(&self, warnings: &mut Vec<String>) {
+        self.root.collect_warnings(warnings);
+    }
+
+    fn collect_min_versions(&self, res: &mut Vec<(&'static str, CmdStanVersion)>) {
+        self.root.collect_min_versions(res);
+        self.output.collect_min_versions(res);
+        if !self.num_threads.is_default() {
+            res.push(("num_threads", CmdStanVersion::new(2, 28, 0)));
+        }
+    }
+
+    fn route_files_into(&mut self, dir: &std::path::Path, version: Option<&CmdStanVersion>) {
+        self.init.route_files_into(dir, version);
+        self.output.route_files_into(dir, version);
+    }
+
+    fn num_chains(&self) -> u32 {
+        self.root.num_chains()
+    }
+
+    fn saves_metric(&self) -> bool {
+        self.root.saves_metric()
+    }
+
+    fn get_chain_files(&self) -> Result<Vec<ChainFiles>, ArgError> {
+        Ok(ChainFiles::plan(&self.output.file, &self.output.diagnostic_file, self.id.id, self.num_chains(), self.saves_metric()))
+    }
 }
 
 impl<T:ArgThrough> WithCommonArgs<T> {
@@ -45,6 +80,60 @@ impl<T:ArgThrough> WithCommonArgs<T> {
             num_threads: ArgNumThreads::ARG_DEFAULT,
         }
     }
+
+    fn args_through(&self, cmd: &mut Command, write_files: bool) -> Result<(), ArgError> {
+        self.root.arg_through(cmd)?;
+        self.id.arg_through(cmd)?;
+        self.data.arg_through(cmd)?;
+        self.init.arg_through_chains(cmd, self.output.file.as_path().parent(), self.id.id, self.root.num_chains(), write_files)?;
+        self.random.arg_through(cmd)?;
+        self.output.arg_through(cmd)?;
+        self.num_threads.arg_through(cmd)?;
+        Ok(())
+    }
+}
+
+/// built from the root node of a CSV header, the method group is passed to `T`
+impl<T:ArgThrough+FromArgNode> FromArgNode for WithCommonArgs<T> {
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let mut res = Self::new(T::from_arg_node(&node.method_group()?)?);
+        if let Some(id) = node.child("id") {
+            res.id = ArgID::from_arg_node(id)?;
+        }
+        if let Some(data) = node.child("data") {
+            res.data = ArgData::from_arg_node(data)?;
+        }
+        if let Some(init) = node.child("init") {
+            res.init = ArgInit::from_arg_node(init)?;
+        }
+        if let Some(random) = node.child("random") {
+            res.random = ArgRandom::from_arg_node(random)?;
+        }
+        if let Some(output) = node.child("output") {
+            res.output = ArgOutput::from_arg_node(output)?;
+        }
+        if let Some(num_threads) = node.child("num_threads") {
+            res.num_threads = ArgNumThreads::from_arg_node(num_threads)?;
+        }
+        Ok(res)
+    }
+}
+
+/// the root node, with the same layout as the CSV header
+impl<T:ArgThrough+ToArgNode> ToArgNode for WithCommonArgs<T> {
+    fn to_arg_node(&self) -> ArgNode {
+        let method = self.root.to_arg_node();
+        let name = method.name.clone();
+        ArgNode::group("", vec![
+            ArgNode::choice("method", &name, "sample", Some(method)),
+            self.id.to_arg_node(),
+            self.data.to_arg_node(),
+            self.init.to_arg_node(),
+            self.random.to_arg_node(),
+            self.output.to_arg_node(),
+            self.num_threads.to_arg_node(),
+        ])
+    }
 }
 
 impl<T:PartialEq+ArgThrough> PartialEq for WithCommonArgs<T> {
@@ -104,6 +193,22 @@ mod common_arg_trees {
                 Ok(())
             }
         }
+
+        impl FromArgNode for ArgID {
+            const NAME: Option<&'static str> = Some("id");
+
+            fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+                let mut res = Self::ARG_DEFAULT;
+                node.expect_name("id")?.read_own_value(&mut res.id)?;
+                Ok(res)
+            }
+        }
+
+        impl ToArgNode for ArgID {
+            fn to_arg_node(&self) -> ArgNode {
+                ArgNode::value("id", &self.id, &Self::ARG_DEFAULT.id)
+            }
+        }
     
         impl ArgID {
             pub fn new() -> ArgID {
@@ -136,6 +241,23 @@ mod common_arg_trees {
                 Ok(())
             }
         }
+
+        impl FromArgNode for ArgData {
+            const NAME: Option<&'static str> = Some("data");
+
+            fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+                let node = node.expect_name("data")?;
+                let mut res = Self::ARG_DEFAULT;
+                arg_from!(node.{file} >> res);
+                Ok(res)
+            }
+        }
+
+        impl ToArgNode for ArgData {
+            fn to_arg_node(&self) -> ArgNode {
+                ArgNode::group("data", arg_to!(self.{file} in Self))
+            }
+        }
     
         impl ArgData {
             pub fn new() -> ArgData {
@@ -151,14 +273,54 @@ mod common_arg_trees {
 
     mod arg_init {
         use super::*;
-        use crate::prelude::DataEntry;
-        use std::collections::hash_map::HashMap;
-
-        #[derive(Debug, PartialEq)]
+        use crate::StanData;
+        use crate::prelude::{DataEntry, DataEntries};
+        use std::path::Path;
+        use std::sync::Arc;
+
+        /// Builds the inits of a chain from its id.
+        pub type InitGenerator = Arc<dyn Fn(u32) -> DataEntries + Send + Sync>;
+
+        /// The inits of a run.
+        ///
+        /// The file of `ParamValue` and `PerChain` is written when the command is built. A bare file name,
+        /// such as the default `init.json`, is put next to the output file of the run rather than in the
+        /// working directory.
+        ///
+        /// With `num_chains > 1`, `PerChain` writes a file for each chain, `init_<chain id>.json`,
+        /// which CmdStan picks up when given `init.json`.
+        #[derive(Clone)]
         pub enum ArgInit {
             Range(f64),
             Path(ArgReadablePath),
-            ParamValue((HashMap<String, DataEntry>, ArgWritablePath)),
+            /// the same inits for every chain, in the order they're written
+            ParamValue((DataEntries, ArgWritablePath)),
+            /// the inits of each chain, from its chain id
+            PerChain((InitGenerator, ArgWritablePath)),
+        }
+
+        impl std::fmt::Debug for ArgInit {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    Self::Range(r) => f.debug_tuple("Range").field(r).finish(),
+                    Self::Path(p) => f.debug_tuple("Path").field(p).finish(),
+                    Self::ParamValue(v) => f.debug_tuple("ParamValue").field(v).finish(),
+                    Self::PerChain((_, file)) => f.debug_tuple("PerChain").field(&format_args!("(.., {file:?})")).finish(),
+                }
+            }
+        }
+
+        /// generators are equal when they're the same closure
+        impl PartialEq for ArgInit {
+            fn eq(&self, other: &Self) -> bool {
+                match (self, other) {
+                    (Self::Range(a), Self::Range(b)) => a == b,
+                    (Self::Path(a), Self::Path(b)) => a == b,
+                    (Self::ParamValue(a), Self::ParamValue(b)) => a == b,
+                    (Self::PerChain((a, fa)), Self::PerChain((b, fb))) => Arc::ptr_eq(a, b) && fa == fb,
+                    _ => false,
+                }
+            }
         }
 
         impl ArgThrough for ArgInit {
@@ -166,37 +328,49 @@ mod common_arg_trees {
                 Err(ArgError::NotValidArgTreeType("ArgInit is not a valid root arg".to_string()))
             }
 
+            /// a single chain with id 1, the file is written as given
             fn arg_through(&self, cmd: &mut Command) -> Result<(), ArgError> {
-                if self.is_default() {
-                    return Ok(());
-                }
+                self.arg_through_chains(cmd, None, 1, 1, true).map(|_| ())
+            }
 
-                match self {
-                    Self::Range(val) => {
-                        cmd.arg(format!("init={}",val));
-                    }
-                    Self::Path(val) => {
-                        cmd.arg(format!("init={}",val));
-                    }
-                    Self::ParamValue((params,file)) => {
-                        let mut param_init = String::new();
+            /// the same argument, without writing the file
+            fn arg_through_dry(&self, cmd: &mut Command) -> Result<(), ArgError> {
+                self.arg_through_chains(cmd, None, 1, 1, false).map(|_| ())
+            }
 
-                        param_init.push_str("{\n");
-                        for (i,(name, initval)) in params.iter().enumerate() {
-                            if i>0 {
-                                param_init.push_str(",\n");
-                            }
-                            param_init.push_str(&format!("    \"{name}\": "));
-                            initval.write_to_stan_json(&mut param_init);
-                        }
-                        param_init.push_str("\n}");
+            /// only the init file written for `ParamValue` and `PerChain` is moved
+            fn route_files_into(&mut self, dir: &Path, version: Option<&CmdStanVersion>) {
+                if let Some(file) = self.param_file_mut() {
+                    *file = file.moved_into(dir);
+                }
+            }
+        }
 
-                        file.write_once(&param_init).map_err(ArgError::FileSystemError)?;
+        /// a number is a `Range`, anything else the `Path` of an init file
+        impl FromArgNode for ArgInit {
+            const NAME: Option<&'static str> = Some("init");
+
+            fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+                let node = node.expect_name("init")?;
+                match node.value.as_deref().filter(|_| !node.is_default) {
+                    None => Ok(Self::ARG_DEFAULT),
+                    Some(init) => Ok(match init.parse() {
+                        Ok(r) => Self::Range(r),
+                        Err(_) => Self::Path(ArgReadablePath::Owned(init.into())),
+                    }),
+                }
+            }
+        }
 
-                        cmd.arg(args_combine("init", file.as_path().as_os_str()));
-                    }
+        /// `ParamValue` and `PerChain` are saved as their file, see `ToArgNode`
+        impl ToArgNode for ArgInit {
+            fn to_arg_node(&self) -> ArgNode {
+                let init = match self {
+                    Self::Range(r) => r.to_string(),
+                    Self::Path(p) => p.to_string(),
+                    Self::ParamValue((_, file)) | Self::PerChain((_, file)) => file.to_string(),
                 };
-                Ok(())
+                ArgNode { is_default: self.is_default(), ..ArgNode::with_value("init", &init) }
             }
         }
 
@@ -219,25 +393,123 @@ mod common_arg_trees {
                 Ok(self)
             }
 
+            /// set the init of `param`, replacing its previous value but keeping its place
+            ///
+            /// the file set by `target_init_by_param_path` is kept when switching from another kind of init
             pub fn set_init_by_param<T: Into<DataEntry>>(&mut self, param: &str, val: T) -> &mut Self {
-                if let Self::ParamValue((p,_)) = self {
-                    p.insert(param.to_string(), val.into());
-                } else {
-                    let mut par = HashMap::new();
-                    par.insert(param.to_string(), val.into());
-                    *self = Self::ParamValue((par, ArgWritablePath::Borrowed("init_params_setup.json")));
+                if !matches!(self, Self::ParamValue(_)) {
+                    let file = self.param_file().cloned().unwrap_or(ArgWritablePath::Borrowed("init.json"));
+                    *self = Self::ParamValue((DataEntries::new(), file));
+                }
+                if let Self::ParamValue((p, _)) = self {
+                    match p.datas.iter_mut().find(|(name, _)| name == param) {
+                        Some((_, v)) => *v = val.into(),
+                        None => { p.add_entry(param, val); }
+                    }
                 }
                 self
             }
 
+            /// set every init at once, written in the order of `inits`
+            pub fn set_init_by_entries(&mut self, inits: DataEntries) -> &mut Self {
+                let file = self.param_file().cloned().unwrap_or(ArgWritablePath::Borrowed("init.json"));
+                *self = Self::ParamValue((inits, file));
+                self
+            }
+
+            /// Build the inits of each chain with `generator`, which receives the chain id.
+            ///
+            /// ```no-run
+            /// args.init.set_init_per_chain(|id| {
+            ///     let mut inits = DataEntries::new();
+            ///     inits.add_entry("mu", f64::from(id) * 0.1);
+            ///     inits
+            /// });
+            /// ```
+            pub fn set_init_per_chain<F: Fn(u32) -> DataEntries + Send + Sync + 'static>(&mut self, generator: F) -> &mut Self {
+                let file = self.param_file().cloned().unwrap_or(ArgWritablePath::Borrowed("init.json"));
+                *self = Self::PerChain((Arc::new(generator), file));
+                self
+            }
+
+            /// set the file the inits are written to
             pub fn target_init_by_param_path(&mut self, file: ArgWritablePath) -> &mut Self {
-                if let Self::ParamValue((_, f)) = self {
-                    *f = file;
-                } else {
-                    *self = Self::ParamValue((HashMap::new(), file));
+                match self {
+                    Self::ParamValue((_, f)) | Self::PerChain((_, f)) => *f = file,
+                    _ => *self = Self::ParamValue((DataEntries::new(), file)),
                 }
                 self
             }
+
+            /// the file the inits are written to, for `ParamValue` and `PerChain`
+            pub fn param_file(&self) -> Option<&ArgWritablePath> {
+                match self {
+                    Self::ParamValue((_, f)) | Self::PerChain((_, f)) => Some(f),
+                    _ => None,
+                }
+            }
+
+            pub(crate) fn param_file_mut(&mut self) -> Option<&mut ArgWritablePath> {
+                match self {
+                    Self::ParamValue((_, f)) | Self::PerChain((_, f)) => Some(f),
+                    _ => None,
+                }
+            }
+
+            /// the init file passed to CmdStan: a bare file name is put in `output_dir`
+            pub fn resolved_file(&self, output_dir: Option<&Path>) -> Option<ArgWritablePath> {
+                let file = self.param_file()?;
+                Some(match output_dir {
+                    Some(dir) => file.resolved_in(dir),
+                    None => file.clone(),
+                })
+            }
+
+            /// Pass the init of a run of `num_chains` chains from `id`, return the init files the command refers to.
+            ///
+            /// The files are written unless `write_files` is false, as for a dry run which only needs their paths.
+            /// see the doc of `ArgInit` for where the files are written
+            pub fn arg_through_chains(&self, cmd: &mut Command, output_dir: Option<&Path>, id: u32, num_chains: u32, write_files: bool) -> Result<Vec<ArgWritablePath>, ArgError> {
+                if self.is_default() {
+                    return Ok(Vec::new());
+                }
+                match self {
+                    Self::Range(val) => {
+                        cmd.arg(format!("init={}",val));
+                        Ok(Vec::new())
+                    }
+                    Self::Path(val) => {
+                        cmd.arg(format!("init={}",val));
+                        Ok(Vec::new())
+                    }
+                    Self::ParamValue(_) | Self::PerChain(_) => {
+                        let Some(file) = self.resolved_file(output_dir) else {
+                            return Ok(Vec::new());
+                        };
+                        let files: Vec<_> = match self {
+                            Self::PerChain(_) if num_chains > 1 => (id..id.saturating_add(num_chains))
+                                .map(|chain| (chain, file.with_stem_suffix(&chain.to_string())))
+                                .collect(),
+                            _ => vec![(id, file.clone())],
+                        };
+                        if write_files {
+                            if let Some(parent) = file.as_path().parent().filter(|p| !p.as_os_str().is_empty()) {
+                                std::fs::create_dir_all(parent).map_err(ArgError::FileSystemError)?;
+                            }
+                            for (chain, chain_file) in &files {
+                                let inits = match self {
+                                    Self::PerChain((generator, _)) => generator(*chain).write_as_stan_data(),
+                                    Self::ParamValue((params, _)) => params.write_as_stan_data(),
+                                    _ => continue,
+                                };
+                                chain_file.write_once(&inits).map_err(ArgError::FileSystemError)?;
+                            }
+                        }
+                        cmd.arg(args_combine("init", file.as_path().as_os_str()));
+                        Ok(files.into_iter().map(|(_, f)| f).collect())
+                    }
+                }
+            }
         }
     }
     pub use arg_init::*;
@@ -261,6 +533,27 @@ mod common_arg_trees {
                 Ok(())
             }
         }
+
+        impl FromArgNode for ArgRandom {
+            const NAME: Option<&'static str> = Some("random");
+
+            /// CmdStan marks a generated seed as default, keep it anyway to reproduce the run
+            fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+                let mut res = Self::ARG_DEFAULT;
+                if let Some(seed) = node.expect_name("random")?.value_of("seed") {
+                    res.seed = FromArgValue::from_arg_value(seed)
+                        .ok_or_else(|| ArgError::BadArgumentValue(format!("random: cannot parse seed = {seed}")))?;
+                }
+                Ok(res)
+            }
+        }
+
+        impl ToArgNode for ArgRandom {
+            fn to_arg_node(&self) -> ArgNode {
+                let seed = self.seed.map(|s| s.to_string()).unwrap_or_default();
+                ArgNode::group("random", vec![ArgNode { is_default: self.seed.is_none(), ..ArgNode::with_value("seed", &seed) }])
+            }
+        }
     
         impl ArgRandom {
             pub fn new() -> ArgRandom {
@@ -290,6 +583,22 @@ mod common_arg_trees {
                 Ok(())
             }
         }
+
+        impl FromArgNode for ArgNumThreads {
+            const NAME: Option<&'static str> = Some("num_threads");
+
+            fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+                let mut res = Self::ARG_DEFAULT;
+                node.expect_name("num_threads")?.read_own_value(&mut res.threads)?;
+                Ok(res)
+            }
+        }
+
+        impl ToArgNode for ArgNumThreads {
+            fn to_arg_node(&self) -> ArgNode {
+                ArgNode::value("num_threads", &self.threads, &Self::ARG_DEFAULT.threads)
+            }
+        }
     
         impl ArgNumThreads {
             pub fn new() -> ArgNumThreads {
@@ -331,6 +640,50 @@ mod common_arg_trees {
             fn get_output_path(&self) -> Result<ArgPath, ArgError> {
                 Ok(ArgPath::from(self.file.clone()))
             }
+
+            fn collect_min_versions(&self, res: &mut Vec<(&'static str, CmdStanVersion)>) {
+                if self.sig_figs != -1 {
+                    res.push(("output sig_figs", CmdStanVersion::new(2, 25, 0)));
+                }
+                if !self.profile_file.is_default() {
+                    res.push(("output profile_file", CmdStanVersion::new(2, 26, 0)));
+                }
+                if self.save_cmdstan_config {
+                    res.push(("output save_cmdstan_config", CmdStanVersion::new(2, 33, 0)));
+                }
+            }
+
+            /// the diagnostic file is only moved when set; the profile file is set to `profile.csv` in `dir`
+            /// if unset, CmdStan writes it to the working directory otherwise, unless `version` is older than
+            /// 2.26, which lacks the argument
+            fn route_files_into(&mut self, dir: &std::path::Path, version: Option<&CmdStanVersion>) {
+                self.file = self.file.moved_into(dir);
+                if !self.diagnostic_file.is_default() {
+                    self.diagnostic_file = self.diagnostic_file.moved_into(dir);
+                }
+                if !self.profile_file.is_default() {
+                    self.profile_file = self.profile_file.moved_into(dir);
+                } else if version.is_none_or(|v| *v >= CmdStanVersion::new(2, 26, 0)) {
+                    self.profile_file = ArgWritablePath::Owned(dir.join("profile.csv"));
+                }
+            }
+        }
+
+        impl FromArgNode for ArgOutput {
+            const NAME: Option<&'static str> = Some("output");
+
+            fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+                let node = node.expect_name("output")?;
+                let mut res = Self::ARG_DEFAULT;
+                arg_from!(node.{file, diagnostic_file, refresh, sig_figs, profile_file, save_cmdstan_config} >> res);
+                Ok(res)
+            }
+        }
+
+        impl ToArgNode for ArgOutput {
+            fn to_arg_node(&self) -> ArgNode {
+                ArgNode::group("output", arg_to!(self.{file, diagnostic_file, refresh, sig_figs, profile_file, save_cmdstan_config} in Self))
+            }
         }
 
         impl ArgOutput {
@@ -373,4 +726,75 @@ mod common_arg_trees {
     ImplDefault!{ArgID, ArgData, ArgInit, ArgRandom, ArgNumThreads, ArgOutput}
 }
 
-pub use common_arg_trees::{ArgID, ArgData, ArgInit, ArgRandom, ArgNumThreads, ArgOutput};
\ No newline at end of file
+pub use common_arg_trees::{ArgID, ArgData, ArgInit, ArgRandom, ArgNumThreads, ArgOutput};
+#[cfg(test)]
+mod arg_init_test {
+    use super::*;
+    use crate::prelude::DataEntries;
+    use crate::stan_command::sample::ArgSample;
+    use std::path::Path;
+
+    #[test]
+    fn test_init_files() {
+        let dir = std::env::temp_dir().join(format!("simple_cmdstan_init_{}", std::process::id()));
+        let mut init = ArgInit::new();
+        init.set_init_by_param("sigma", 1.0).set_init_by_param("mu", vec![0.5, 1.5]).set_init_by_param("sigma", 2.0);
+        let mut cmd = Command::new("m");
+        let files = init.arg_through_chains(&mut cmd, Some(&dir), 3, 2, true).unwrap();
+        assert_eq!(files, [ArgWritablePath::Owned(dir.join("init.json"))]);
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), [args_combine("init", dir.join("init.json").as_os_str())]);
+        assert_eq!(std::fs::read_to_string(dir.join("init.json")).unwrap(), "{\n    \"sigma\": 2,\n    \"mu\": [0.5, 1.5]\n}");
+
+        init.set_init_per_chain(|id| {
+            let mut inits = DataEntries::new();
+            inits.add_entry("mu", id as i32);
+            inits
+        });
+        // several chains get a file each, CmdStan finds them from the name passed
+        let mut cmd = Command::new("m");
+        let files = init.arg_through_chains(&mut cmd, Some(&dir), 3, 2, true).unwrap();
+        assert_eq!(files, [ArgWritablePath::Owned(dir.join("init_3.json")), ArgWritablePath::Owned(dir.join("init_4.json"))]);
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), [args_combine("init", dir.join("init.json").as_os_str())]);
+        for id in [3, 4] {
+            assert_eq!(std::fs::read_to_string(dir.join(format!("init_{id}.json"))).unwrap(), format!("{{\n    \"mu\": {id}\n}}"));
+        }
+
+        // a dry run names the same files without writing them
+        std::fs::remove_dir_all(&dir).unwrap();
+        let planned = init.arg_through_chains(&mut Command::new("m"), Some(&dir), 3, 2, false).unwrap();
+        assert_eq!(planned, files);
+        assert!(!dir.exists());
+
+        // a single chain writes the file as named, from the generator
+        let files = init.arg_through_chains(&mut Command::new("m"), Some(&dir), 5, 1, true).unwrap();
+        assert_eq!(files, [ArgWritablePath::Owned(dir.join("init.json"))]);
+        assert_eq!(std::fs::read_to_string(dir.join("init.json")).unwrap(), "{\n    \"mu\": 5\n}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_init_eq() {
+        let mut init = ArgInit::new();
+        init.set_init_per_chain(|_| DataEntries::new());
+        let mut other = ArgInit::new();
+        other.set_init_per_chain(|_| DataEntries::new());
+        assert_eq!(init.clone(), init);
+        assert_ne!(init, other);
+    }
+
+    #[test]
+    fn test_init_file_kept() {
+        let mut init = ArgInit::new();
+        init.set_init_per_chain(|_| DataEntries::new()).target_init_by_param_path(ArgWritablePath::Borrowed("warm.json"));
+        init.set_init_by_param("mu", 0.5);
+        assert_eq!(init.param_file(), Some(&ArgWritablePath::Borrowed("warm.json")));
+
+        // through ArgThrough, a single chain whose file is named as given
+        let mut cmd = Command::new("m");
+        init.arg_through_dry(&mut cmd).unwrap();
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), [args_combine("init", "warm.json".as_ref())]);
+        assert!(!Path::new("warm.json").exists());
+        init.route_files_into(Path::new("run"), None);
+        assert_eq!(init.param_file(), Some(&ArgWritablePath::Owned(Path::new("run").join("warm.json"))));
+    }
+}
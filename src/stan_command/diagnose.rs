@@ -28,6 +28,45 @@ impl ArgThrough for ArgDiagnose {
     }
 }
 
+impl FromArgNode for ArgDiagnose {
+    const METHOD: Option<&'static str> = Some("diagnose");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let node = node.expect_name("diagnose")?;
+        let test = node.child("test");
+        match test.and_then(|t| t.chosen()) {
+            None => Ok(Self::ARG_DEFAULT),
+            Some(("gradient", g)) => Ok(Self::Gradient(g.map(ArgDiagnoseGradient::from_arg_node).transpose()?.unwrap_or_default())),
+            Some((x, _)) => Err(ArgError::BadArgumentValue(format!("diagnose: unknown test {x}"))),
+        }
+    }
+}
+
+impl FromArgNode for ArgDiagnoseGradient {
+    const NAME: Option<&'static str> = Some("gradient");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let node = node.expect_name("gradient")?;
+        let mut res = Self::ARG_DEFAULT;
+        arg_from!(node.{epsilon, error} >> res);
+        Ok(res)
+    }
+}
+
+impl ToArgNode for ArgDiagnose {
+    fn to_arg_node(&self) -> ArgNode {
+        let Self::Gradient(g) = self;
+        let test = ArgNode::choice("test", "gradient", "gradient", Some(g.to_arg_node()));
+        ArgNode::group("diagnose", vec![test])
+    }
+}
+
+impl ToArgNode for ArgDiagnoseGradient {
+    fn to_arg_node(&self) -> ArgNode {
+        ArgNode::group("gradient", arg_to!(self.{epsilon, error} in Self))
+    }
+}
+
 impl ArgDiagnose {
     pub fn new() -> Self {
         Self::ARG_DEFAULT
@@ -0,0 +1,87 @@
+use super::arg_tree::*;
+use std::ffi::{OsStr, OsString};
+use std::process::Command;
+
+/// Render the command line `arg_into` would run, the model path first, without running it.
+///
+/// Nothing is written: the init files of an `ArgInit::ParamValue` or `ArgInit::PerChain` are named
+/// where `arg_into` would write them, see `ArgThrough::arg_through_dry`.
+pub fn render_argv<T:ArgThrough>(arg_tree: &T, model_path: &ArgPath) -> Result<Vec<OsString>, ArgError> {
+    let mut cmd = Command::new(model_path.as_path());
+    arg_tree.arg_through_dry(&mut cmd)?;
+    let mut res = vec![cmd.get_program().to_os_string()];
+    res.extend(cmd.get_args().map(OsStr::to_os_string));
+    Ok(res)
+}
+
+/// Render the command line of `render_argv` as a single line to paste into a POSIX shell.
+///
+/// ```no-run
+/// let args = WithCommonArgs::new(ArgSample::new().with_num_samples(500)?);
+/// let line = render_shell_command(&args, &ArgPath::from("./bernoulli"))?;
+/// assert_eq!(line, "./bernoulli sample num_samples=500");
+/// ```
+pub fn render_shell_command<T:ArgThrough>(arg_tree: &T, model_path: &ArgPath) -> Result<String, ArgError> {
+    let argv = render_argv(arg_tree, model_path)?;
+    Ok(argv.iter().map(|a| shell_quote(a)).collect::<Vec<_>>().join(" "))
+}
+
+/// Render the tree view of `arg_tree`, see `ArgNode`'s `Display`.
+///
+/// ```text
+/// * method = sample
+/// *   sample
+/// *     num_samples = 500
+///       num_warmup = 1000
+/// ```
+pub fn render_tree<T:ToArgNode>(arg_tree: &T) -> String {
+    arg_tree.to_arg_node().to_string()
+}
+
+/// quote `arg` with single quotes unless it only has characters the shell leaves alone
+fn shell_quote(arg: &OsStr) -> String {
+    let arg = arg.to_string_lossy();
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "_-./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_plain) {
+        arg.into_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod dry_run_test {
+    use super::*;
+    use crate::stan_command::common_arg::WithCommonArgs;
+    use crate::stan_command::sample::ArgSample;
+
+    #[test]
+    fn test_render() {
+        let mut args = WithCommonArgs::new(ArgSample::new().with_num_samples(500).unwrap());
+        args.data.set_file(ArgReadablePath::Borrowed("my data.json"));
+        let line = render_shell_command(&args, &ArgPath::from("./model")).unwrap();
+        assert_eq!(line, "./model sample num_samples=500 data 'file=my data.json'");
+
+        let tree = render_tree(&args);
+        assert!(tree.contains("*     num_samples = 500\n"));
+        assert!(tree.contains("      num_warmup = 1000\n"));
+        assert!(tree.contains("*   file = my data.json\n"));
+
+        let node = args.to_arg_node();
+        assert_eq!(WithCommonArgs::<ArgSample>::from_arg_node(&node).unwrap().root, args.root);
+    }
+
+    #[test]
+    fn test_render_writes_nothing() {
+        let dir = std::env::temp_dir().join(format!("simple_cmdstan_dry_run_{}", std::process::id()));
+        let mut args = WithCommonArgs::new(ArgSample::new().with_num_chains(2).unwrap());
+        args.output.set_file(ArgWritablePath::Owned(dir.join("output.csv")));
+        args.init.set_init_by_param("mu", 0.5);
+        let argv = render_argv(&args, &ArgPath::from("./model")).unwrap();
+        assert!(argv.contains(&args_combine("init", dir.join("init.json").as_os_str())));
+
+        args.init.set_init_per_chain(|_| crate::prelude::DataEntries::new());
+        render_argv(&args, &ArgPath::from("./model")).unwrap();
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,165 @@
+use super::arg_tree::*;
+use std::ffi::{OsStr, OsString};
+use std::process::Command;
+
+/// An arg tree passing raw arguments the typed trees don't model, at given places of the hierarchy.
+///
+/// Everything else is delegated to `inner`.
+///
+/// ```no-run
+/// let args = WithExtraArgs::new(WithCommonArgs::new(ArgSample::new()))
+///     .with_args_at(&["sample", "adapt"], ["new_adapt_option=1"])
+///     .with_args_at(&["output"], ["new_output_option=true"]);
+/// // model sample adapt new_adapt_option=1 output new_output_option=true
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithExtraArgs<T:ArgThrough> {
+    pub inner: T,
+    /// (keyword path, raw arguments), in the order they're inserted
+    pub extra: Vec<(Vec<String>, Vec<OsString>)>,
+}
+
+impl<T:ArgThrough> WithExtraArgs<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner, extra: Vec::new() }
+    }
+
+    /// Insert `args` right after the keywords of `path`, e.g. `&["sample", "adapt"]`.
+    ///
+    /// A keyword matches an argument naming it (`adapt`), setting it (`algorithm=hmc` for `algorithm`),
+    /// or choosing it (`algorithm=hmc` for `hmc`). Keywords the inner tree doesn't pass are added,
+    /// and an empty path appends `args` at the end.
+    pub fn add_args_at<S: AsRef<OsStr>>(&mut self, path: &[&str], args: impl IntoIterator<Item = S>) -> &mut Self {
+        self.extra.push((
+            path.iter().map(|k| k.to_string()).collect(),
+            args.into_iter().map(|a| a.as_ref().to_os_string()).collect(),
+        ));
+        self
+    }
+
+    /// see `add_args_at`
+    pub fn with_args_at<S: AsRef<OsStr>>(mut self, path: &[&str], args: impl IntoIterator<Item = S>) -> Self {
+        self.add_args_at(path, args);
+        self
+    }
+
+    /// pass the arguments `inner_through` gives, with the extra arguments inserted
+    fn insert_extra(&self, cmd: &mut Command, inner_through: impl FnOnce(&mut Command) -> Result<(), ArgError>) -> Result<(), ArgError> {
+        let mut scratch = Command::new("");
+        inner_through(&mut scratch)?;
+        let mut args: Vec<OsString> = scratch.get_args().map(OsStr::to_os_string).collect();
+        for (path, extra) in &self.extra {
+            insert_at(&mut args, path, extra);
+        }
+        cmd.args(args);
+        Ok(())
+    }
+}
+
+fn matches_keyword(arg: &OsStr, keyword: &str) -> bool {
+    let arg = arg.to_string_lossy();
+    if arg == keyword {
+        return true;
+    }
+    match arg.split_once('=') {
+        Some((key, value)) => key == keyword || value == keyword,
+        None => false,
+    }
+}
+
+/// insert `extra` into `args` after the keywords of `path`, adding the missing ones
+fn insert_at(args: &mut Vec<OsString>, path: &[String], extra: &[OsString]) {
+    let mut cursor = 0;
+    let mut missing = Vec::new();
+    for (i, keyword) in path.iter().enumerate() {
+        match args[cursor..].iter().position(|a| matches_keyword(a, keyword)) {
+            Some(p) => cursor += p + 1,
+            None => {
+                missing = path[i..].iter().map(OsString::from).collect();
+                if i == 0 {
+                    cursor = args.len();
+                }
+                break;
+            }
+        }
+    }
+    let inserted: Vec<_> = missing.into_iter().chain(extra.iter().cloned()).collect();
+    if path.is_empty() {
+        cursor = args.len();
+    }
+    args.splice(cursor..cursor, inserted);
+}
+
+impl<T:ArgThrough> ArgThrough for WithExtraArgs<T> {
+    fn arg_type(&self) -> Result<ArgType, ArgError> {
+        self.inner.arg_type()
+    }
+
+    fn arg_through(&self, cmd: &mut Command) -> Result<(), ArgError> {
+        self.insert_extra(cmd, |scratch| self.inner.arg_through(scratch))
+    }
+
+    fn arg_through_dry(&self, cmd: &mut Command) -> Result<(), ArgError> {
+        self.insert_extra(cmd, |scratch| self.inner.arg_through_dry(scratch))
+    }
+
+    fn get_id(&self) -> u32 {
+        self.inner.get_id()
+    }
+
+    fn get_output_path(&self) -> Result<ArgPath, ArgError> {
+        self.inner.get_output_path()
+    }
+
+    fn collect_problems(&self, problems: &mut Vec<String>) {
+        self.inner.collect_problems(problems);
+    }
+
+    fn collect_warnings(&self, warnings: &mut Vec<String>) {
+        self.inner.collect_warnings(warnings);
+    }
+
+    fn collect_min_versions(&self, res: &mut Vec<(&'static str, CmdStanVersion)>) {
+        self.inner.collect_min_versions(res);
+    }
+
+    fn route_files_into(&mut self, dir: &std::path::Path, version: Option<&CmdStanVersion>) {
+        self.inner.route_files_into(dir, version);
+    }
+
+    fn num_chains(&self) -> u32 {
+        self.inner.num_chains()
+    }
+
+    fn saves_metric(&self) -> bool {
+        self.inner.saves_metric()
+    }
+
+    fn get_chain_files(&self) -> Result<Vec<ChainFiles>, ArgError> {
+        self.inner.get_chain_files()
+    }
+}
+
+#[cfg(test)]
+mod extra_args_test {
+    use super::*;
+    use crate::stan_command::render_argv;
+    use crate::stan_command::common_arg::WithCommonArgs;
+    use crate::stan_command::sample::ArgSample;
+
+    #[test]
+    fn test_extra_args() {
+        let mut inner = WithCommonArgs::new(ArgSample::new().with_num_samples(10).unwrap());
+        inner.root.adapt.set_delta(0.9).unwrap();
+        let args = WithExtraArgs::new(inner)
+            .with_args_at(&["sample", "adapt"], ["a=1"])
+            .with_args_at(&["sample", "algorithm=hmc", "engine=nuts"], ["b=2"])
+            .with_args_at(&["output"], ["c=3"])
+            .with_args_at(&[], ["d=4"]);
+        let argv: Vec<_> = render_argv(&args, &ArgPath::from("m")).unwrap().into_iter().skip(1).collect();
+        assert_eq!(argv, [
+            "sample", "algorithm=hmc", "engine=nuts", "b=2", "num_samples=10", "adapt", "a=1", "delta=0.9",
+            "output", "c=3", "d=4",
+        ]);
+    }
+}
@@ -0,0 +1,312 @@
+use super::arg_tree::*;
+use super::sample::ArgSample;
+use super::optimize::ArgOptimize;
+use super::variational::ArgVariational;
+use super::diagnose::ArgDiagnose;
+use super::generate_quantities::ArgGenerate;
+use super::pathfinder::ArgPathfinder;
+use super::log_prob::ArgLogProb;
+use super::laplace::ArgLaplace;
+use super::common_arg::WithCommonArgs;
+use super::extra_args::WithExtraArgs;
+use super::method::StanMethod;
+use crate::error::{CmdStanError, ParamError};
+use crate::result_analyzer::{analyze_gradient_csv, AsResult, StanCsv};
+use std::path::Path;
+
+/// An arg tree whose output file can be read into a typed result.
+pub trait WithFit: ArgThrough {
+    /// the result of the method, e.g. `SampleFit` for `ArgSample`
+    type Fit;
+
+    /// read the output file of a run made with these arguments
+    fn read_fit(&self, output_file: &Path) -> Result<Self::Fit, CmdStanError>;
+}
+
+/// The inverse metric found by the adaptation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvMetric {
+    Diag(Vec<f64>),
+    Dense(Vec<Vec<f64>>),
+}
+
+/// The adaptation info CmdStan writes after the warmup draws.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Adaptation {
+    pub stepsize: f64,
+    pub inv_metric: Option<InvMetric>,
+}
+
+impl Adaptation {
+    /// read the comments following `Adaptation terminated`, None if there're none
+    pub fn from_comments<'a>(mut comments: impl Iterator<Item = &'a str>) -> Option<Self> {
+        comments.find(|c| *c == "Adaptation terminated")?;
+        let stepsize = comments.next()?.strip_prefix("Step size =")?.trim().parse().ok()?;
+        let parse_row = |c: &str| c.split(',').map(|v| v.trim().parse::<f64>()).collect::<Result<Vec<_>, _>>().ok();
+        let inv_metric = match comments.next() {
+            Some("Diagonal elements of inverse mass matrix:") => comments.next().and_then(parse_row).map(InvMetric::Diag),
+            Some("Elements of inverse mass matrix:") => {
+                let rows: Vec<_> = comments.map_while(parse_row).collect();
+                Some(InvMetric::Dense(rows))
+            }
+            _ => None,
+        };
+        Some(Self { stepsize, inv_metric })
+    }
+}
+
+/// The result of `sample`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleFit {
+    /// the warmup draws, which are only saved with `save_warmup`
+    pub warmup: StanCsv,
+    pub draws: StanCsv,
+    /// None without adaptation, e.g. for the fixed_param sampler
+    pub adaptation: Option<Adaptation>,
+    /// the elapsed seconds of the warmup and of the sampling
+    pub elapsed: Option<(f64, f64)>,
+}
+
+impl WithFit for ArgSample {
+    type Fit = SampleFit;
+
+    fn read_fit(&self, output_file: &Path) -> Result<SampleFit, CmdStanError> {
+        let mut warmup = StanCsv::read(output_file)?;
+        let adaptation = Adaptation::from_comments(warmup.comments.iter().map(|(_, c)| c.as_str()));
+        let num_warmup = match warmup.comments.iter().find(|(_, c)| c == "Adaptation terminated") {
+            Some((row, _)) => *row,
+            None if self.save_warmup && self.algorithm.get_hmc().is_some() => self.num_warmup.div_ceil(self.thin.max(1)) as usize,
+            None => 0,
+        };
+        let draws = warmup.split_rows_off(num_warmup);
+        let seconds = |phase: &str| draws.comments.iter()
+            .find_map(|(_, c)| c.trim_start_matches("Elapsed Time:").trim().strip_suffix(phase))
+            .and_then(|c| c.trim().strip_suffix("seconds")?.trim().parse().ok());
+        let elapsed = seconds("(Warm-up)").zip(seconds("(Sampling)"));
+        Ok(SampleFit { warmup, draws, adaptation, elapsed })
+    }
+}
+
+/// The result of `optimize`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizeFit {
+    /// one row for each iteration with `save_iterations`, the last row is the mode
+    pub iterations: StanCsv,
+}
+
+impl OptimizeFit {
+    /// the values of the mode, in the order of `iterations.columns`
+    pub fn mode(&self) -> Option<&[f64]> {
+        self.iterations.rows.last().map(Vec::as_slice)
+    }
+
+    /// the value of `name` at the mode
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.iterations.get(self.iterations.rows.len().checked_sub(1)?, name)
+    }
+
+    pub fn lp(&self) -> Option<f64> {
+        self.get("lp__")
+    }
+}
+
+impl WithFit for ArgOptimize {
+    type Fit = OptimizeFit;
+
+    fn read_fit(&self, output_file: &Path) -> Result<OptimizeFit, CmdStanError> {
+        Ok(OptimizeFit { iterations: StanCsv::read(output_file)? })
+    }
+}
+
+/// The result of `variational`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariationalFit {
+    /// the mean of the approximation, in the order of `draws.columns`
+    pub mean: Vec<f64>,
+    pub draws: StanCsv,
+}
+
+impl VariationalFit {
+    /// the mean of `name` in the approximation
+    pub fn mean_of(&self, name: &str) -> Option<f64> {
+        self.mean.get(self.draws.column_index(name)?).copied()
+    }
+}
+
+impl WithFit for ArgVariational {
+    type Fit = VariationalFit;
+
+    /// the first row CmdStan writes is the mean, the draws follow
+    fn read_fit(&self, output_file: &Path) -> Result<VariationalFit, CmdStanError> {
+        let mut mean = StanCsv::read(output_file)?;
+        let draws = mean.split_rows_off(1);
+        Ok(VariationalFit { mean: mean.rows.pop().unwrap_or_default(), draws })
+    }
+}
+
+/// One parameter of the gradient check of `diagnose`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GradientCheck {
+    pub param_idx: usize,
+    pub value: f64,
+    pub model: f64,
+    pub finite_diff: f64,
+    pub error: f64,
+}
+
+/// The result of `diagnose`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiagnoseFit {
+    pub lp: Option<f64>,
+    pub gradients: Vec<GradientCheck>,
+}
+
+impl DiagnoseFit {
+    /// whether every gradient is within `threshold` of the finite difference, e.g. the `error` argument
+    pub fn passed(&self, threshold: f64) -> bool {
+        self.gradients.iter().all(|g| g.error.abs() <= threshold)
+    }
+}
+
+impl AsResult for DiagnoseFit {
+    fn new_line(&mut self) {
+        self.gradients.push(GradientCheck::default());
+    }
+
+    fn set_value(&mut self, key: &str, val: &str) -> Result<(), ParamError> {
+        let Some(g) = self.gradients.last_mut() else {
+            return Ok(());
+        };
+        let parse = |v: &str| v.parse::<f64>().map_err(|e| ParamError::ParseError(Box::new(e)));
+        match key {
+            "param_idx" => g.param_idx = val.parse().map_err(|e| ParamError::ParseError(Box::new(e)))?,
+            "value" => g.value = parse(val)?,
+            "model" => g.model = parse(val)?,
+            "finite_diff" => g.finite_diff = parse(val)?,
+            "error" => g.error = parse(val)?,
+            _ => return Err(ParamError::ParamNotFound(key.to_string())),
+        }
+        Ok(())
+    }
+}
+
+impl WithFit for ArgDiagnose {
+    type Fit = DiagnoseFit;
+
+    fn read_fit(&self, output_file: &Path) -> Result<DiagnoseFit, CmdStanError> {
+        let mut res = DiagnoseFit {
+            lp: StanCsv::read(output_file)?.comments.iter()
+                .find_map(|(_, c)| c.strip_prefix("Log probability=")?.trim().parse().ok()),
+            gradients: Vec::new(),
+        };
+        analyze_gradient_csv(ArgReadablePath::Owned(output_file.to_path_buf()), &mut res)?;
+        Ok(res)
+    }
+}
+
+macro_rules! draws_fit (
+    ($($(#[$doc:meta])* $arg:ty),+) => {
+        $($(#[$doc])* impl WithFit for $arg {
+            type Fit = StanCsv;
+
+            fn read_fit(&self, output_file: &Path) -> Result<StanCsv, CmdStanError> {
+                StanCsv::read(output_file)
+            }
+        })+
+    };
+);
+
+draws_fit!{
+    /// the generated quantities of each draw of the fitted params
+    ArgGenerate,
+    /// the approximate draws, with `lp_approx__` and `lp__`
+    ArgPathfinder,
+    /// one row for each parameter set, with `lp__` and the gradients
+    ArgLogProb,
+    /// the approximate draws, with `log_p__` and `log_g__`
+    ArgLaplace
+}
+
+/// The result of a `StanMethod`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StanFit {
+    Sample(SampleFit),
+    Optimize(OptimizeFit),
+    Variational(VariationalFit),
+    Diagnose(DiagnoseFit),
+    GenerateQuantities(StanCsv),
+    Pathfinder(StanCsv),
+    LogProb(StanCsv),
+    Laplace(StanCsv),
+}
+
+impl WithFit for StanMethod {
+    type Fit = StanFit;
+
+    fn read_fit(&self, output_file: &Path) -> Result<StanFit, CmdStanError> {
+        Ok(match self {
+            Self::Sample(m) => StanFit::Sample(m.read_fit(output_file)?),
+            Self::Optimize(m) => StanFit::Optimize(m.read_fit(output_file)?),
+            Self::Variational(m) => StanFit::Variational(m.read_fit(output_file)?),
+            Self::Diagnose(m) => StanFit::Diagnose(m.read_fit(output_file)?),
+            Self::GenerateQuantities(m) => StanFit::GenerateQuantities(m.read_fit(output_file)?),
+            Self::Pathfinder(m) => StanFit::Pathfinder(m.read_fit(output_file)?),
+            Self::LogProb(m) => StanFit::LogProb(m.read_fit(output_file)?),
+            Self::Laplace(m) => StanFit::Laplace(m.read_fit(output_file)?),
+        })
+    }
+}
+
+impl<T:WithFit> WithFit for WithCommonArgs<T> {
+    type Fit = T::Fit;
+
+    fn read_fit(&self, output_file: &Path) -> Result<T::Fit, CmdStanError> {
+        self.root.read_fit(output_file)
+    }
+}
+
+impl<T:WithFit> WithFit for WithExtraArgs<T> {
+    type Fit = T::Fit;
+
+    fn read_fit(&self, output_file: &Path) -> Result<T::Fit, CmdStanError> {
+        self.inner.read_fit(output_file)
+    }
+}
+
+#[cfg(test)]
+mod fit_test {
+    use super::*;
+
+    #[test]
+    fn test_sample_fit() {
+        let fit = ArgSample::new().read_fit(Path::new("examples/bernoulli/output.csv")).unwrap();
+        assert!(fit.warmup.rows.is_empty());
+        assert_eq!(fit.draws.rows.len(), 1000);
+        assert_eq!(fit.draws.columns.last().map(String::as_str), Some("theta"));
+        assert_eq!(fit.adaptation, Some(Adaptation { stepsize: 1.01208, inv_metric: Some(InvMetric::Diag(vec![0.584056])) }));
+        assert_eq!(fit.elapsed, Some((0.002, 0.007)));
+    }
+
+    #[test]
+    fn test_split_warmup() {
+        let text = "\
+# method = sample
+lp__,stepsize__,mu
+-1,0.5,0.1
+-2,0.5,0.2
+# Adaptation terminated
+# Step size = 0.8
+# Elements of inverse mass matrix:
+# 1, 0.5
+# 0.5, 2
+-3,0.8,0.3
+";
+        let mut warmup = StanCsv::parse(text).unwrap();
+        let adaptation = Adaptation::from_comments(warmup.comments.iter().map(|(_, c)| c.as_str())).unwrap();
+        assert_eq!(adaptation.inv_metric, Some(InvMetric::Dense(vec![vec![1.0, 0.5], vec![0.5, 2.0]])));
+        let draws = warmup.split_rows_off(2);
+        assert_eq!((warmup.rows.len(), draws.rows.len()), (2, 1));
+        assert_eq!(draws.get(0, "mu"), Some(0.3));
+        assert_eq!(warmup.column("lp__"), Some(vec![-1.0, -2.0]));
+    }
+}
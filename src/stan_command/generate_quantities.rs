@@ -18,6 +18,23 @@ impl ArgGenerate {
     }
 }
 
+impl FromArgNode for ArgGenerate {
+    const METHOD: Option<&'static str> = Some("generate_quantities");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let node = node.expect_name("generate_quantities")?;
+        let mut res = Self::ARG_DEFAULT;
+        arg_from!(node.{fitted_params, num_chains} >> res);
+        Ok(res)
+    }
+}
+
+impl ToArgNode for ArgGenerate {
+    fn to_arg_node(&self) -> ArgNode {
+        ArgNode::group("generate_quantities", arg_to!(self.{fitted_params, num_chains} in Self))
+    }
+}
+
 impl ArgThrough for ArgGenerate {
     fn arg_type(&self) -> Result<ArgType, ArgError> {
         Ok(ArgType::GenerateQuantities)
@@ -28,4 +45,14 @@ impl ArgThrough for ArgGenerate {
         arg_into!(self.{num_chains, fitted_params} in Self >> cmd);
         Ok(())
     }
+
+    fn collect_min_versions(&self, res: &mut Vec<(&'static str, CmdStanVersion)>) {
+        if self.num_chains != 1 {
+            res.push(("generate_quantities num_chains", CmdStanVersion::new(2, 33, 0)));
+        }
+    }
+
+    fn num_chains(&self) -> u32 {
+        self.num_chains
+    }
 }
\ No newline at end of file
@@ -19,6 +19,33 @@ impl ArgThrough for ArgLaplace {
         arg_into!(self.{mode, jacobian, draws, calculate_lp} in Self >> cmd);
         Ok(())
     }
+
+    fn collect_problems(&self, problems: &mut Vec<String>) {
+        if self.mode.is_default() {
+            problems.push("Laplace: mode is required".to_string());
+        }
+    }
+
+    fn collect_min_versions(&self, res: &mut Vec<(&'static str, CmdStanVersion)>) {
+        res.push(("laplace", CmdStanVersion::new(2, 32, 0)));
+    }
+}
+
+impl FromArgNode for ArgLaplace {
+    const METHOD: Option<&'static str> = Some("laplace");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let node = node.expect_name("laplace")?;
+        let mut res = Self::ARG_DEFAULT;
+        arg_from!(node.{mode, jacobian, draws, calculate_lp} >> res);
+        Ok(res)
+    }
+}
+
+impl ToArgNode for ArgLaplace {
+    fn to_arg_node(&self) -> ArgNode {
+        ArgNode::group("laplace", arg_to!(self.{mode, jacobian, draws, calculate_lp} in Self))
+    }
 }
 
 impl ArgLaplace {
@@ -18,6 +18,35 @@ impl ArgThrough for ArgLogProb {
         arg_into!(self.{unconstrained_params, constrained_params, jacobian} in Self >> cmd);
         Ok(())
     }
+
+    fn collect_problems(&self, problems: &mut Vec<String>) {
+        match (self.unconstrained_params.is_default(), self.constrained_params.is_default()) {
+            (true, true) => problems.push("LogProb: either unconstrained_params or constrained_params is required".to_string()),
+            (false, false) => problems.push("LogProb: unconstrained_params and constrained_params cannot be both set".to_string()),
+            _ => {}
+        }
+    }
+
+    fn collect_min_versions(&self, res: &mut Vec<(&'static str, CmdStanVersion)>) {
+        res.push(("log_prob", CmdStanVersion::new(2, 31, 0)));
+    }
+}
+
+impl FromArgNode for ArgLogProb {
+    const METHOD: Option<&'static str> = Some("log_prob");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let node = node.expect_name("log_prob")?;
+        let mut res = Self::ARG_DEFAULT;
+        arg_from!(node.{unconstrained_params, constrained_params, jacobian} >> res);
+        Ok(res)
+    }
+}
+
+impl ToArgNode for ArgLogProb {
+    fn to_arg_node(&self) -> ArgNode {
+        ArgNode::group("log_prob", arg_to!(self.{unconstrained_params, constrained_params, jacobian} in Self))
+    }
 }
 
 impl ArgLogProb {
@@ -0,0 +1,205 @@
+use super::arg_tree::*;
+use super::sample::ArgSample;
+use super::optimize::ArgOptimize;
+use super::variational::ArgVariational;
+use super::diagnose::ArgDiagnose;
+use super::generate_quantities::ArgGenerate;
+use super::pathfinder::ArgPathfinder;
+use super::log_prob::ArgLogProb;
+use super::laplace::ArgLaplace;
+use crate::error::CmdStanError;
+use crate::result_analyzer::{analyze_csv, analyze_gradient_csv, AsResult};
+use std::process::Command;
+use std::str::FromStr;
+
+macro_rules! stan_methods (
+    ($($variant:ident($arg:ty)),+$(,)?) => {
+        /// Any method tree, to choose the method at runtime, e.g. from a config file or a command line flag.
+        ///
+        /// ```no-run
+        /// let method: StanMethod = "optimize".parse()?;
+        /// let args = WithCommonArgs::new(method);
+        /// let args = WithCommonArgs::<StanMethod>::load_config(Path::new("run.toml"))?;
+        /// ```
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum StanMethod {
+            $($variant($arg)),+
+        }
+
+        $(impl From<$arg> for StanMethod {
+            fn from(value: $arg) -> Self {
+                Self::$variant(value)
+            }
+        })+
+
+        impl ArgThrough for StanMethod {
+            fn arg_type(&self) -> Result<ArgType, ArgError> {
+                match self { $(Self::$variant(m) => m.arg_type()),+ }
+            }
+
+            fn arg_through(&self, cmd: &mut Command) -> Result<(), ArgError> {
+                match self { $(Self::$variant(m) => m.arg_through(cmd)),+ }
+            }
+
+            fn collect_problems(&self, problems: &mut Vec<String>) {
+                match self { $(Self::$variant(m) => m.collect_problems(problems)),+ }
+            }
+
+            fn collect_warnings(&self, warnings: &mut Vec<String>) {
+                match self { $(Self::$variant(m) => m.collect_warnings(warnings)),+ }
+            }
+
+            fn collect_min_versions(&self, res: &mut Vec<(&'static str, CmdStanVersion)>) {
+                match self { $(Self::$variant(m) => m.collect_min_versions(res)),+ }
+            }
+
+            fn num_chains(&self) -> u32 {
+                match self { $(Self::$variant(m) => m.num_chains()),+ }
+            }
+
+            fn saves_metric(&self) -> bool {
+                match self { $(Self::$variant(m) => m.saves_metric()),+ }
+            }
+        }
+
+        impl ToArgNode for StanMethod {
+            fn to_arg_node(&self) -> ArgNode {
+                match self { $(Self::$variant(m) => m.to_arg_node()),+ }
+            }
+        }
+
+        /// built from a method group, such as `sample`, the method is told by the name of the group
+        impl FromArgNode for StanMethod {
+            fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+                $(if Some(node.name.as_str()) == <$arg>::METHOD {
+                    return <$arg>::from_arg_node(node).map(Self::$variant);
+                })+
+                Err(ArgError::BadArgumentValue(format!("unknown method {}", node.name)))
+            }
+
+            fn from_root_node(root: &ArgNode) -> Result<Self, ArgError> {
+                Self::from_arg_node(&root.method_group()?)
+            }
+        }
+
+        impl StanMethod {
+            /// the name CmdStan gives the method, e.g. `sample`
+            pub fn name(&self) -> &'static str {
+                match self { $(Self::$variant(_) => <$arg>::METHOD.unwrap_or_default()),+ }
+            }
+
+            /// the method with every argument default, by the name CmdStan gives it
+            pub fn from_name(name: &str) -> Option<Self> {
+                $(if Some(name) == <$arg>::METHOD {
+                    return Some(Self::$variant(<$arg>::ARG_DEFAULT));
+                })+
+                None
+            }
+        }
+    };
+);
+
+stan_methods!{
+    Sample(ArgSample),
+    Optimize(ArgOptimize),
+    Variational(ArgVariational),
+    Diagnose(ArgDiagnose),
+    GenerateQuantities(ArgGenerate),
+    Pathfinder(ArgPathfinder),
+    LogProb(ArgLogProb),
+    Laplace(ArgLaplace),
+}
+
+impl StanMethod {
+    /// Read the output file of a run of this method into `res`.
+    ///
+    /// `diagnose` writes its gradient table as comments, which is read with `analyze_gradient_csv`;
+    /// the other methods write a table of draws, or a single line for `optimize`, read with `analyze_csv`.
+    pub fn analyze_output<R:AsResult>(&self, output_file: ArgReadablePath, res: &mut R) -> Result<(), CmdStanError> {
+        match self {
+            Self::Diagnose(_) => analyze_gradient_csv(output_file, res),
+            _ => analyze_csv(output_file, res),
+        }
+    }
+}
+
+impl WithDefaultArg for StanMethod {
+    const ARG_DEFAULT: Self = Self::Sample(ArgSample::ARG_DEFAULT);
+}
+
+impl Default for StanMethod {
+    fn default() -> Self {
+        Self::ARG_DEFAULT
+    }
+}
+
+/// parse the name of a method, such as `sample`, into the method with every argument default
+impl FromStr for StanMethod {
+    type Err = ArgError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_name(s.trim()).ok_or_else(|| ArgError::BadArgumentValue(format!("unknown method {s}")))
+    }
+}
+
+#[cfg(test)]
+mod method_test {
+    use super::*;
+    use crate::stan_command::common_arg::WithCommonArgs;
+    use crate::stan_command::render_argv;
+    use crate::result_analyzer::GRADIENT_COLUMNS;
+
+    #[derive(Default)]
+    struct Rows(Vec<Vec<(String, String)>>);
+
+    impl AsResult for Rows {
+        fn new_line(&mut self) {
+            self.0.push(Vec::new());
+        }
+
+        fn set_value(&mut self, key: &str, val: &str) -> Result<(), crate::error::ParamError> {
+            self.0.last_mut().unwrap().push((key.to_string(), val.to_string()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let method: StanMethod = "pathfinder".parse().unwrap();
+        assert_eq!(method, StanMethod::Pathfinder(ArgPathfinder::new()));
+        assert_eq!(method.arg_type().unwrap(), ArgType::Pathfinder);
+        assert!("nuts".parse::<StanMethod>().is_err());
+
+        let args = WithCommonArgs::new(StanMethod::from(ArgOptimize::new().with_jacobian(true)));
+        let argv: Vec<_> = render_argv(&args, &ArgPath::from("m")).unwrap().into_iter().skip(1).collect();
+        assert_eq!(argv, ["optimize", "jacobian=true"]);
+        assert!(args.check_version(&CmdStanVersion::new(2, 30, 0)).is_err());
+
+        let loaded = WithCommonArgs::<StanMethod>::from_json_str(&args.to_json_string(true)).unwrap();
+        assert_eq!(loaded, args);
+        let parsed = StanMethod::from_arg_string("./m variational iter=500").unwrap();
+        assert_eq!(parsed, StanMethod::Variational(ArgVariational::new().with_iter(500).unwrap()));
+    }
+
+    #[test]
+    fn test_analyze_gradient() {
+        let path = std::env::temp_dir().join(format!("simple_cmdstan_gradient_{}.csv", std::process::id()));
+        std::fs::write(&path, "\
+# method = diagnose
+# Log probability=3.218
+#
+# param idx           value           model     finite diff           error
+#         0          1.6518        -1.20832        -1.20832    -9.70445e-10
+#         1         -1.5389       -0.258025       -0.258025     1.29734e-09
+#
+").unwrap();
+        let mut rows = Rows::default();
+        let res = StanMethod::Diagnose(ArgDiagnose::new()).analyze_output(ArgReadablePath::Owned(path.clone()), &mut rows);
+        std::fs::remove_file(&path).unwrap();
+        res.unwrap();
+        assert_eq!(rows.0.len(), 2);
+        let keys: Vec<_> = rows.0[1].iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, GRADIENT_COLUMNS);
+        assert_eq!(rows.0[1][2].1, "-0.258025");
+    }
+}
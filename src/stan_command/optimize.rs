@@ -65,6 +65,94 @@ impl ArgThrough for ArgOptimize {
         arg_into!(self.{jacobian, iter, save_iterations} in Self >> cmd);
         Ok(())
     }
+
+    fn collect_min_versions(&self, res: &mut Vec<(&'static str, CmdStanVersion)>) {
+        if self.jacobian {
+            res.push(("optimize jacobian", CmdStanVersion::new(2, 32, 0)));
+        }
+    }
+}
+
+impl FromArgNode for ArgOptimize {
+    const METHOD: Option<&'static str> = Some("optimize");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let node = node.expect_name("optimize")?;
+        let mut res = Self::ARG_DEFAULT;
+        arg_from!(node.{jacobian, iter, save_iterations} >> res);
+        if let Some(algorithm) = node.child("algorithm") {
+            res.algorithm = ArgOptimizeAlgorithm::from_arg_node(algorithm)?;
+        }
+        Ok(res)
+    }
+}
+
+/// built from the choice `algorithm = lbfgs`
+impl FromArgNode for ArgOptimizeAlgorithm {
+    const NAME: Option<&'static str> = Some("algorithm");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let bfgs = |group: Option<&ArgNode>| group.map(ArgOptimizeBfgs::from_arg_node).transpose().map(Option::unwrap_or_default);
+        match node.expect_name("algorithm")?.chosen() {
+            Some(("newton", _)) => Ok(Self::Newton),
+            Some(("bfgs", group)) => Ok(Self::Bfgs(bfgs(group)?)),
+            Some(("lbfgs", group)) => {
+                let mut history_size = 5;
+                if let Some(group) = group {
+                    group.read_value("history_size", &mut history_size)?;
+                }
+                Ok(Self::LBfgs(bfgs(group)?, history_size))
+            }
+            _ => Err(ArgError::BadArgumentValue(format!("optimize: unknown algorithm {:?}", node.value))),
+        }
+    }
+}
+
+/// built from either the `bfgs` or the `lbfgs` group
+impl FromArgNode for ArgOptimizeBfgs {
+    const NAME: Option<&'static str> = Some("bfgs");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let mut res = Self::ARG_DEFAULT;
+        arg_from!(node.{init_alpha, tol_obj, tol_rel_obj, tol_grad, tol_rel_grad, tol_param} >> res);
+        Ok(res)
+    }
+}
+
+impl ToArgNode for ArgOptimize {
+    fn to_arg_node(&self) -> ArgNode {
+        let mut children = vec![self.algorithm.to_arg_node()];
+        children.extend(arg_to!(self.{jacobian, iter, save_iterations} in Self));
+        ArgNode::group("optimize", children)
+    }
+}
+
+impl ToArgNode for ArgOptimizeAlgorithm {
+    fn to_arg_node(&self) -> ArgNode {
+        match self {
+            Self::Newton => ArgNode::choice("algorithm", "newton", "lbfgs", None),
+            Self::Bfgs(b) => ArgNode::choice("algorithm", "bfgs", "lbfgs", Some(b.to_arg_node())),
+            Self::LBfgs(b, v) => {
+                let mut children = b.to_arg_children();
+                children.push(ArgNode::value("history_size", v, &5));
+                ArgNode::choice("algorithm", "lbfgs", "lbfgs", Some(ArgNode::group("lbfgs", children)))
+            }
+        }
+    }
+}
+
+/// the `bfgs` group, the `lbfgs` group has the same values and `history_size`
+impl ToArgNode for ArgOptimizeBfgs {
+    fn to_arg_node(&self) -> ArgNode {
+        ArgNode::group("bfgs", self.to_arg_children())
+    }
+}
+
+impl ArgOptimizeBfgs {
+    /// the values shared by the `bfgs` and `lbfgs` groups
+    fn to_arg_children(&self) -> Vec<ArgNode> {
+        Vec::from(arg_to!(self.{init_alpha, tol_obj, tol_rel_obj, tol_grad, tol_rel_grad, tol_param} in Self))
+    }
 }
 
 impl ArgOptimize {
@@ -29,6 +29,37 @@ impl ArgThrough for ArgPathfinder {
         arg_into!(self.{init_alpha, tol_obj, tol_rel_obj, tol_grad, tol_rel_grad, tol_param, history_size, num_psis_draws, num_paths, save_single_paths, psis_resample, calculate_lp, max_lbfgs_iters, num_draws, num_elbo_draws} in Self >> cmd);
         Ok(())
     }
+
+    fn collect_problems(&self, problems: &mut Vec<String>) {
+        if self.psis_resample && !self.calculate_lp {
+            problems.push("Pathfinder: psis_resample requires calculate_lp".to_string());
+        }
+        if u64::from(self.num_psis_draws) > u64::from(self.num_draws) * u64::from(self.num_paths) {
+            problems.push(format!("Pathfinder: num_psis_draws ({}) exceeds num_draws * num_paths ({} * {})",
+                self.num_psis_draws, self.num_draws, self.num_paths));
+        }
+    }
+
+    fn collect_min_versions(&self, res: &mut Vec<(&'static str, CmdStanVersion)>) {
+        res.push(("pathfinder", CmdStanVersion::new(2, 33, 0)));
+    }
+}
+
+impl FromArgNode for ArgPathfinder {
+    const METHOD: Option<&'static str> = Some("pathfinder");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let node = node.expect_name("pathfinder")?;
+        let mut res = Self::ARG_DEFAULT;
+        arg_from!(node.{init_alpha, tol_obj, tol_rel_obj, tol_grad, tol_rel_grad, tol_param, history_size, num_psis_draws, num_paths, save_single_paths, psis_resample, calculate_lp, max_lbfgs_iters, num_draws, num_elbo_draws} >> res);
+        Ok(res)
+    }
+}
+
+impl ToArgNode for ArgPathfinder {
+    fn to_arg_node(&self) -> ArgNode {
+        ArgNode::group("pathfinder", arg_to!(self.{init_alpha, tol_obj, tol_rel_obj, tol_grad, tol_rel_grad, tol_param, history_size, num_psis_draws, num_paths, save_single_paths, psis_resample, calculate_lp, max_lbfgs_iters, num_draws, num_elbo_draws} in Self))
+    }
 }
 
 impl ArgPathfinder {
@@ -0,0 +1,287 @@
+use super::arg_tree::*;
+use super::{prepare_command, finish_result, StanResult};
+use super::run_options::{RunOptions, incomplete_marker};
+use crate::error::Interruption;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressPhase {
+    Warmup,
+    Sampling,
+}
+
+/// A parsed CmdStan progress line, such as `Chain [2] Iteration:  200 / 2000 [ 10%]  (Warmup)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressEvent {
+    /// the chain id, from the `Chain [n]` prefix or the id of the run
+    pub chain: u32,
+    pub iteration: u32,
+    pub total: u32,
+    pub percent: u32,
+    pub phase: ProgressPhase,
+    /// time since the process was started
+    pub elapsed: Duration,
+}
+
+impl ProgressEvent {
+    /// Parse a progress line, return None if the line isn't one.
+    pub fn parse(line: &str, default_chain: u32, elapsed: Duration) -> Option<Self> {
+        let mut line = line.trim();
+        let mut chain = default_chain;
+        if let Some(rest) = line.strip_prefix("Chain") {
+            let (id, rest) = rest.trim_start().strip_prefix('[')?.split_once(']')?;
+            chain = id.trim().parse().ok()?;
+            line = rest.trim_start();
+        }
+
+        let rest = line.strip_prefix("Iteration:")?;
+        let (iteration, rest) = rest.split_once('/')?;
+        let (total, rest) = rest.split_once('[')?;
+        let (percent, rest) = rest.split_once('%')?;
+        let phase = match rest.trim_start_matches(']').trim() {
+            "(Warmup)" => ProgressPhase::Warmup,
+            "(Sampling)" => ProgressPhase::Sampling,
+            _ => return None,
+        };
+
+        Some(Self {
+            chain,
+            iteration: iteration.trim().parse().ok()?,
+            total: total.trim().parse().ok()?,
+            percent: percent.trim().parse().ok()?,
+            phase,
+            elapsed,
+        })
+    }
+}
+
+/// What a running Stan process reports.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StanEvent {
+    /// a progress line of stdout, parsed
+    Progress(ProgressEvent),
+    /// any other line of stdout
+    Stdout(String),
+    /// a line of stderr
+    Stderr(String),
+}
+
+fn forward_lines<R: Read>(reader: R, is_stderr: bool, tx: Sender<(bool, String)>) {
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    while let Ok(n) = reader.read_until(b'\n', &mut buf) {
+        if n == 0 || tx.send((is_stderr, String::from_utf8_lossy(&buf).into_owned())).is_err() {
+            break;
+        }
+        buf.clear();
+    }
+}
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// keep the partial files of an interrupted run, and leave a marker file next to each of them
+fn mark_incomplete(chains: &[ChainFiles], reason: &Interruption) -> ArgError {
+    for file in chains.iter().flat_map(ChainFiles::files) {
+        if let Err(e) = std::fs::write(incomplete_marker(file.as_path()), format!("{reason}\n")) {
+            return ArgError::FileSystemError(e);
+        }
+    }
+    let output = chains.first().map(|c| c.output.as_path().into()).unwrap_or_default();
+    ArgError::Interrupted(reason.clone(), output)
+}
+
+/// Spawn `cmd`, report its output line by line to `on_event` while it runs, and collect the whole output.
+///
+/// The process is killed when `options` says so, and every file of `chains` is marked as incomplete.
+pub(crate) fn execute(cmd: &mut Command, chains: &[ChainFiles], default_chain: u32, options: &RunOptions, on_event: &mut dyn FnMut(StanEvent)) -> Result<Output, ArgError> {
+    let start = Instant::now();
+    let mut child = cmd.stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(ArgError::FileSystemError)?;
+
+    let (tx, rx) = mpsc::channel();
+    let readers = [
+        child.stdout.take().map(|r| { let tx = tx.clone(); std::thread::spawn(move || forward_lines(r, false, tx)) }),
+        child.stderr.take().map(|r| { let tx = tx.clone(); std::thread::spawn(move || forward_lines(r, true, tx)) }),
+    ];
+    drop(tx);
+
+    let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+    let mut streaming = true;
+    let status = loop {
+        if let Some(reason) = options.interruption(start) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(mark_incomplete(chains, &reason));
+        }
+
+        if !streaming {
+            match child.try_wait().map_err(ArgError::FileSystemError)? {
+                Some(status) => break status,
+                None => std::thread::sleep(POLL_INTERVAL),
+            }
+            continue;
+        }
+
+        let (is_stderr, line) = match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(msg) => msg,
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                streaming = false;
+                continue;
+            }
+        };
+        let text = line.trim_end_matches(['\r', '\n']).to_string();
+        if is_stderr {
+            stderr.extend_from_slice(line.as_bytes());
+            on_event(StanEvent::Stderr(text));
+        } else {
+            stdout.extend_from_slice(line.as_bytes());
+            match ProgressEvent::parse(&text, default_chain, start.elapsed()) {
+                Some(p) => on_event(StanEvent::Progress(p)),
+                None => on_event(StanEvent::Stdout(text)),
+            }
+        }
+    };
+
+    for r in readers.into_iter().flatten() {
+        let _ = r.join();
+    }
+    for marker in chains.iter().flat_map(ChainFiles::files).map(|f| incomplete_marker(f.as_path())) {
+        if marker.exists() {
+            std::fs::remove_file(marker).map_err(ArgError::FileSystemError)?;
+        }
+    }
+    Ok(Output { status, stdout, stderr })
+}
+
+/// Same as `arg_into`, but spawns the model and reports its output line by line while it runs.
+///
+/// The collected output is still available in the `StanResult`.
+///
+/// ```no-run
+/// let res = arg_into_with_progress(&args, &model_path, |e| {
+///     if let StanEvent::Progress(p) = e {
+///         println!("chain {}: {}%", p.chain, p.percent);
+///     }
+/// })?;
+/// ```
+pub fn arg_into_with_progress<T, F>(arg_tree: &T, model_path: &ArgPath, mut on_event: F) -> Result<StanResult<T>, ArgError>
+where
+    T: ArgThrough+Clone,
+    F: FnMut(StanEvent),
+{
+    arg_into_with_options(arg_tree, model_path, &RunOptions::default(), on_event)
+}
+
+/// Same as `arg_into_with_progress`, killing the process on timeout or cancel.
+///
+/// With `options.run_dir`, the files are routed into a new directory, which the `StanResult` holds
+/// as `run_dir`; the `arg_tree` of the result has the routed paths.
+///
+/// # Errors
+///
+/// - when the run is interrupted, returns ArgError::Interrupted with the output file of the first chain;
+///   the partial files are kept and `<file>.incomplete` is written next to each file of every chain.
+/// - a failed run always keeps its directory.
+///
+/// ```no-run
+/// let token = CancelToken::new();
+/// let options = RunOptions::new().with_timeout(Some(Duration::from_secs(3600))).with_cancel(Some(token.clone()));
+/// let res = arg_into_with_options(&args, &model_path, &options, |_| {});
+/// ```
+pub fn arg_into_with_options<T, F>(arg_tree: &T, model_path: &ArgPath, options: &RunOptions, mut on_event: F) -> Result<StanResult<T>, ArgError>
+where
+    T: ArgThrough+Clone,
+    F: FnMut(StanEvent),
+{
+    let Some(policy) = &options.run_dir else {
+        let (mut cmd, output_path) = prepare_command(arg_tree, model_path)?;
+        let output = execute(&mut cmd, &arg_tree.get_chain_files()?, arg_tree.get_id(), options, &mut on_event)?;
+        return finish_result(arg_tree, output_path, output);
+    };
+
+    let mut run_dir = policy.create(model_path.as_path()).map_err(ArgError::FileSystemError)?;
+    let mut arg_tree = arg_tree.clone();
+    arg_tree.route_files_into(run_dir.path(), CmdStanVersion::detect(model_path.as_path()).as_ref());
+    let res = prepare_command(&arg_tree, model_path).and_then(|(mut cmd, output_path)| {
+        let output = execute(&mut cmd, &arg_tree.get_chain_files()?, arg_tree.get_id(), options, &mut on_event)?;
+        finish_result(&arg_tree, output_path, output)
+    });
+    match res {
+        Ok(mut res) => {
+            res.run_dir = Some(run_dir);
+            Ok(res)
+        }
+        Err(e) => {
+            run_dir.keep();
+            Err(e)
+        }
+    }
+}
+
+/// Same as `arg_into_with_progress`, delivering the events through a channel.
+///
+/// A closed receiver doesn't stop the run.
+pub fn arg_into_with_channel<T:ArgThrough+Clone>(arg_tree: &T, model_path: &ArgPath, sender: Sender<StanEvent>) -> Result<StanResult<T>, ArgError> {
+    arg_into_with_progress(arg_tree, model_path, |e| { let _ = sender.send(e); })
+}
+
+#[cfg(test)]
+mod progress_test {
+    use super::*;
+
+    #[test]
+    fn test_parse_progress() {
+        let elapsed = Duration::from_secs(3);
+        let p = ProgressEvent::parse("Iteration:  200 / 2000 [ 10%]  (Warmup)", 1, elapsed).unwrap();
+        assert_eq!(p, ProgressEvent { chain: 1, iteration: 200, total: 2000, percent: 10, phase: ProgressPhase::Warmup, elapsed });
+
+        let p = ProgressEvent::parse("Chain [3] Iteration: 2000 / 2000 [100%]  (Sampling)", 1, elapsed).unwrap();
+        assert_eq!((p.chain, p.iteration, p.percent, p.phase), (3, 2000, 100, ProgressPhase::Sampling));
+
+        assert_eq!(ProgressEvent::parse("Gradient evaluation took 1e-05 seconds", 1, elapsed), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_interrupt() {
+        use super::super::run_options::CancelToken;
+        let dir = std::env::temp_dir().join(format!("simple_cmdstan_interrupt_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = ArgWritablePath::Owned(dir.join("output.csv"));
+        let chains = ChainFiles::plan(&output, &ArgWritablePath::Owned(dir.join("diag.csv")), 1, 2, false);
+        let markers: Vec<_> = ["output_1.csv", "diag_1.csv", "output_2.csv", "diag_2.csv"].iter()
+            .map(|f| incomplete_marker(&dir.join(f)))
+            .collect();
+
+        let options = RunOptions::new().with_timeout(Some(Duration::from_millis(200)));
+        let start = Instant::now();
+        match execute(Command::new("sleep").arg("10"), &chains, 1, &options, &mut |_| {}) {
+            Err(ArgError::Interrupted(Interruption::Timeout(t), path)) => {
+                assert_eq!((t, path), (Duration::from_millis(200), dir.join("output_1.csv")));
+            }
+            r => panic!("expected a timeout, found {r:?}"),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(markers.iter().all(|m| m.is_file()), "{markers:?}");
+
+        // a finished run removes the markers it left before
+        execute(&mut Command::new("true"), &chains, 1, &RunOptions::new(), &mut |_| {}).unwrap();
+        assert!(markers.iter().all(|m| !m.exists()));
+
+        let token = CancelToken::new();
+        token.cancel();
+        let options = RunOptions::new().with_cancel(Some(token));
+        match execute(Command::new("sleep").arg("10"), &chains, 1, &options, &mut |_| {}) {
+            Err(ArgError::Interrupted(Interruption::Cancelled, _)) => {}
+            r => panic!("expected a cancel, found {r:?}"),
+        }
+        assert_eq!(std::fs::read_to_string(&markers[3]).unwrap(), format!("{}\n", Interruption::Cancelled));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+use super::arg_tree::*;
+use crate::stan_model::ContentHasher;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where runs write their files: a new directory under `parent` for each run,
+/// named `<timestamp>-<model>-<hash>`, e.g. `20260118-093015-bernoulli-3f9a0c12`.
+///
+/// The output, diagnostic, profile and init files of the run are put into it, see `ArgThrough::route_files_into`,
+/// and so are the metric files CmdStan writes next to the output file.
+/// The chains of a `ChainRunner` share the directory of the run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunDirPolicy {
+    pub parent: PathBuf,
+    /// remove the directory when the `StanResult` holding it is dropped
+    pub delete_on_drop: bool,
+}
+
+impl RunDirPolicy {
+    pub fn new(parent: impl Into<PathBuf>) -> Self {
+        Self { parent: parent.into(), delete_on_drop: false }
+    }
+
+    default_setter!{
+        <"Remove the directory when the `StanResult` holding it is dropped">(delete_on_drop: bool;);
+    }
+
+    /// create the directory of a new run of `model_path`
+    pub fn create(&self, model_path: &Path) -> std::io::Result<RunDir> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let model = model_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "model".to_string());
+        std::fs::create_dir_all(&self.parent)?;
+        loop {
+            let hash = ContentHasher::new()
+                .update_field(model_path.as_os_str().as_encoded_bytes())
+                .update(&now.as_nanos().to_le_bytes())
+                .update(&std::process::id().to_le_bytes())
+                .update(&COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes())
+                .finish_hex();
+            let path = self.parent.join(format!("{}-{model}-{}", utc_timestamp(now.as_secs()), &hash[..8]));
+            match std::fs::create_dir(&path) {
+                Ok(()) => return Ok(RunDir { path, delete_on_drop: self.delete_on_drop }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// The directory of a run, removed on drop if `delete_on_drop`.
+#[derive(Debug)]
+pub struct RunDir {
+    path: PathBuf,
+    delete_on_drop: bool,
+}
+
+impl RunDir {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn delete_on_drop(&self) -> bool {
+        self.delete_on_drop
+    }
+
+    /// keep the directory when dropped
+    pub fn keep(&mut self) -> &mut Self {
+        self.delete_on_drop = false;
+        self
+    }
+
+    /// keep the directory and return its path
+    pub fn into_path(mut self) -> PathBuf {
+        self.keep();
+        std::mem::take(&mut self.path)
+    }
+}
+
+impl Drop for RunDir {
+    fn drop(&mut self) {
+        if self.delete_on_drop {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// `YYYYMMDD-HHMMSS` of the UTC time `secs` seconds after the epoch
+fn utc_timestamp(secs: u64) -> String {
+    let (days, rem) = (secs / 86400, secs % 86400);
+    // civil date from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}{month:02}{day:02}-{:02}{:02}{:02}", rem / 3600, rem / 60 % 60, rem % 60)
+}
+
+#[cfg(test)]
+mod run_dir_test {
+    use super::*;
+    use crate::stan_command::common_arg::{WithCommonArgs, ArgInit};
+    use crate::stan_command::sample::ArgSample;
+
+    #[test]
+    fn test_utc_timestamp() {
+        assert_eq!(utc_timestamp(0), "19700101-000000");
+        assert_eq!(utc_timestamp(1709210096), "20240229-123456");
+    }
+
+    #[test]
+    fn test_run_dir() {
+        let parent = std::env::temp_dir().join(format!("simple_cmdstan_runs_{}", std::process::id()));
+        let policy = RunDirPolicy::new(&parent).with_delete_on_drop(true);
+        let a = policy.create(Path::new("models/bernoulli.exe")).unwrap();
+        let b = policy.create(Path::new("models/bernoulli.exe")).unwrap();
+        assert_ne!(a.path(), b.path());
+        let name = a.path().file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.contains("-bernoulli-"), "{name}");
+
+        let mut args = WithCommonArgs::new(ArgSample::new());
+        args.output.set_diagnostic_file(ArgWritablePath::Borrowed("out/diag.csv"));
+        args.init.set_init_by_param("mu", 0.5);
+        let mut old = args.clone();
+        args.route_files_into(a.path(), None);
+        assert_eq!(args.output.file.as_path(), a.path().join("output.csv"));
+        assert_eq!(args.output.diagnostic_file.as_path(), a.path().join("diag.csv"));
+        assert_eq!(args.output.profile_file.as_path(), a.path().join("profile.csv"));
+        // CmdStan before 2.26 has no profile_file argument
+        old.route_files_into(a.path(), Some(&CmdStanVersion::new(2, 25, 0)));
+        assert!(old.output.profile_file.is_default());
+        match &args.init {
+            ArgInit::ParamValue((_, file)) => assert_eq!(file.as_path(), a.path().join("init.json")),
+            init => panic!("expected ParamValue, found {init:?}"),
+        }
+
+        let kept = b.into_path();
+        let dropped = a.path().to_path_buf();
+        drop(a);
+        assert!(!dropped.exists());
+        assert!(kept.exists());
+        std::fs::remove_dir_all(&parent).unwrap();
+    }
+}
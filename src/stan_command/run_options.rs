@@ -0,0 +1,63 @@
+use super::arg_tree::*;
+use super::run_dir::RunDirPolicy;
+use crate::error::Interruption;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::time::{Duration, Instant};
+
+/// A handle to cancel runs from another thread. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// kill every run using this token
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Limits of a run. On timeout or cancel the process is killed, the partial files of every chain are kept
+/// and each marked by a `<file>.incomplete` file, and `ArgError::Interrupted` is returned.
+///
+/// With `run_dir`, each run writes its files into a new directory, see `RunDirPolicy`.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub timeout: Option<Duration>,
+    pub cancel: Option<CancelToken>,
+    pub run_dir: Option<RunDirPolicy>,
+}
+
+impl RunOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    default_setter!{
+        <"Kill the process when it runs longer than this">(timeout: Option<Duration>;);
+        <"Kill the process when this token is cancelled">(cancel: Option<CancelToken>;);
+        <"Write the files of each run into a new directory">(run_dir: Option<RunDirPolicy>;);
+    }
+
+    /// return why the run should stop now, if it should
+    pub(crate) fn interruption(&self, start: Instant) -> Option<Interruption> {
+        if self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+            return Some(Interruption::Cancelled);
+        }
+        self.timeout.filter(|t| start.elapsed() >= *t).map(Interruption::Timeout)
+    }
+}
+
+/// the marker file of an interrupted run writing `output`
+pub fn incomplete_marker(output: &Path) -> PathBuf {
+    let mut name = output.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".incomplete");
+    output.with_file_name(name)
+}
@@ -0,0 +1,299 @@
+use super::arg_tree::*;
+use super::{arg_into_with_options, StanResult, RunOptions, RunDir};
+use super::common_arg::{WithCommonArgs, ArgInit};
+use super::sample::ArgSample;
+use crate::error::RuntimeError;
+use std::sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}};
+
+/// What to change, besides the seed, before retrying a chain which failed to initialize.
+#[derive(Clone)]
+pub enum RetryStrategy {
+    /// only draw other inits with a new seed
+    NewSeed,
+    /// Multiply the init range by this factor on each attempt, e.g. 0.5 for (-2, 2), (-1, 1), (-0.5, 0.5).
+    ///
+    /// The factor must be in (0, 1), see `RetryPolicy::new`.
+    /// inits which aren't a range are replaced by the narrowed default range
+    NarrowRange(f64),
+    /// the inits returned for (chain id, attempt), attempts counting from 1 for the first retry
+    Generator(Arc<dyn Fn(u32, u32) -> ArgInit + Send + Sync>),
+}
+
+impl std::fmt::Debug for RetryStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NewSeed => write!(f, "NewSeed"),
+            Self::NarrowRange(factor) => f.debug_tuple("NarrowRange").field(factor).finish(),
+            Self::Generator(_) => write!(f, "Generator(..)"),
+        }
+    }
+}
+
+/// Retry a chain which fails with `RuntimeError::InitializationFailed`, with a new seed each attempt.
+///
+/// The seed of the attempt `k` of the chain `i` is `base seed + i + k * num_chains`, so it's
+/// reproducible and doesn't collide with the seeds of the other chains.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// the number of runs of a chain, the first included
+    pub max_attempts: u32,
+    pub strategy: RetryStrategy,
+}
+
+impl RetryPolicy {
+    /// # Errors
+    ///
+    /// - when the factor of `RetryStrategy::NarrowRange` isn't in (0, 1) returns BadArgumentValue,
+    ///   it would give CmdStan an init range of 0 or less, or widen the range
+    pub fn new(max_attempts: u32, strategy: RetryStrategy) -> Result<Self, ArgError> {
+        if let RetryStrategy::NarrowRange(factor) = strategy
+            && !(factor > 0.0 && factor < 1.0) {
+            return Err(ArgError::BadArgumentValue(format!("RetryPolicy: NarrowRange expected 0 < factor < 1, found {factor}")));
+        }
+        Ok(Self { max_attempts, strategy })
+    }
+
+    /// the arguments of the attempt `attempt` (from 1) of a chain, which previously ran with `args`
+    pub fn next_args<T: ArgThrough+Clone>(&self, args: &WithCommonArgs<T>, attempt: u32, num_chains: u32) -> WithCommonArgs<T> {
+        let mut res = args.clone();
+        res.random.seed = args.random.seed.map(|s| s.wrapping_add(num_chains.max(1)));
+        match &self.strategy {
+            RetryStrategy::NewSeed => {}
+            RetryStrategy::NarrowRange(factor) => {
+                let range = match args.init {
+                    ArgInit::Range(r) => r,
+                    _ => 2.0, // the default of CmdStan
+                };
+                res.init = ArgInit::Range(range * factor);
+            }
+            RetryStrategy::Generator(generate) => res.init = generate(args.id.id, attempt),
+        }
+        res
+    }
+}
+
+/// A run of a chain which failed to initialize and was retried.
+#[derive(Debug)]
+pub struct FailedAttempt {
+    pub seed: Option<u32>,
+    pub init: ArgInit,
+    pub error: ArgError,
+}
+
+/// Runs a chain per process, several processes at a time.
+///
+/// Each chain `i` (counting from 0) gets
+/// - the id `base.id + i`,
+/// - the seed `base.seed + i` (a random base seed is drawn if `base.seed` is None),
+/// - output, diagnostic, profile and init files suffixed with its id, e.g. `output_1.csv`.
+///
+/// `options` applies to every chain, so one cancel token stops all of them. With `options.run_dir`,
+/// a single directory is made for the run, and every chain writes its files into it.
+/// With `retry`, a chain which fails to initialize is run again, see `RetryPolicy`.
+#[derive(Debug, Clone)]
+pub struct ChainRunner<T: ArgThrough> {
+    pub base: WithCommonArgs<T>,
+    pub num_chains: u32,
+    pub max_parallel: usize,
+    pub options: RunOptions,
+    pub retry: Option<RetryPolicy>,
+}
+
+/// The results of every chain, keyed by chain id. A failed chain doesn't affect the others.
+#[derive(Debug)]
+pub struct ChainResults<T: ArgThrough> {
+    pub succeeded: Vec<(u32, StanResult<WithCommonArgs<T>>)>,
+    pub failed: Vec<(u32, ArgError)>,
+    /// the attempts before the last run of each retried chain, see `RetryPolicy`
+    pub retried: Vec<(u32, Vec<FailedAttempt>)>,
+    /// the directory of the run made by `RunOptions::run_dir`, kept if any chain failed
+    pub run_dir: Option<RunDir>,
+}
+
+impl<T: ArgThrough> ChainResults<T> {
+    pub fn all_succeeded(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl<T: ArgThrough+Clone+Send+Sync> ChainRunner<T> {
+    /// run `num_chains` chains of `base`, as many in parallel as the machine has cores
+    pub fn new(base: WithCommonArgs<T>, num_chains: u32) -> Self {
+        Self {
+            base,
+            num_chains,
+            max_parallel: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            options: RunOptions::default(),
+            retry: None,
+        }
+    }
+
+    default_setter!{
+        <"Number of chains">(num_chains: u32; num_chains==0 => "ChainRunner: num_chains cannot be 0".to_string());
+        <"Maximum number of chains running at the same time">
+            (max_parallel: usize; max_parallel==0 => "ChainRunner: max_parallel cannot be 0".to_string());
+        <"Timeout and cancel token of every chain">(options: RunOptions;);
+        <"Retry the chains which fail to initialize">(retry: Option<RetryPolicy>;);
+    }
+
+    /// the arguments of the chain with 0-based index `index`, deriving its seed from `base_seed`
+    pub fn chain_args(&self, index: u32, base_seed: u32) -> WithCommonArgs<T> {
+        let mut res = self.base.clone();
+        let id = self.base.id.id + index;
+        let suffix = id.to_string();
+        res.id.id = id;
+        res.random.seed = Some(base_seed.wrapping_add(index));
+
+        res.output.file = res.output.file.with_stem_suffix(&suffix);
+        if !res.output.diagnostic_file.is_default() {
+            res.output.diagnostic_file = res.output.diagnostic_file.with_stem_suffix(&suffix);
+        }
+        if !res.output.profile_file.is_default() {
+            res.output.profile_file = res.output.profile_file.with_stem_suffix(&suffix);
+        }
+        if let Some(file) = res.init.param_file_mut() {
+            *file = file.with_stem_suffix(&suffix);
+        }
+        res
+    }
+
+    /// run a chain with `options`, retrying it as `retry` says; return the last result and the failed attempts before it
+    fn run_chain(&self, job: &WithCommonArgs<T>, model_path: &ArgPath, options: &RunOptions) -> (Result<StanResult<WithCommonArgs<T>>, ArgError>, Vec<FailedAttempt>) {
+        let mut history = Vec::new();
+        let mut args = job.clone();
+        let mut attempt = 0;
+        loop {
+            let res = arg_into_with_options(&args, model_path, options, |_| {});
+            let can_retry = self.retry.as_ref().filter(|r| attempt + 1 < r.max_attempts);
+            match (res, can_retry) {
+                (Err(error @ ArgError::Runtime(RuntimeError::InitializationFailed(_), _)), Some(retry)) => {
+                    attempt += 1;
+                    let next = retry.next_args(&args, attempt, self.num_chains);
+                    history.push(FailedAttempt { seed: args.random.seed, init: args.init, error });
+                    args = next;
+                }
+                (res, _) => return (res, history),
+            }
+        }
+    }
+
+    /// Run every chain as a separate process of `model_path` and wait for all of them.
+    ///
+    /// If the directory of `options.run_dir` can't be made, every chain fails with the error.
+    pub fn run(&self, model_path: &ArgPath) -> ChainResults<T> {
+        let base_seed = self.base.random.seed.unwrap_or_else(rand::random);
+        let mut res = ChainResults { succeeded: Vec::new(), failed: Vec::new(), retried: Vec::new(), run_dir: None };
+        // the chains share one directory, so route the base into it and run each chain without a policy of its own
+        let mut runner = self.clone();
+        if let Some(policy) = runner.options.run_dir.take() {
+            match policy.create(model_path.as_path()) {
+                Ok(dir) => {
+                    runner.base.route_files_into(dir.path(), CmdStanVersion::detect(model_path.as_path()).as_ref());
+                    res.run_dir = Some(dir);
+                }
+                Err(e) => {
+                    for i in 0..self.num_chains {
+                        res.failed.push((self.base.id.id + i, ArgError::FileSystemError(std::io::Error::new(e.kind(), e.to_string()))));
+                    }
+                    return res;
+                }
+            }
+        }
+        let options = &runner.options;
+        let jobs: Vec<_> = (0..self.num_chains).map(|i| runner.chain_args(i, base_seed)).collect();
+        let next = AtomicUsize::new(0);
+        let finished = Mutex::new(Vec::with_capacity(jobs.len()));
+
+        std::thread::scope(|s| {
+            for _ in 0..self.max_parallel.clamp(1, jobs.len().max(1)) {
+                s.spawn(|| {
+                    while let Some(job) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let (res, history) = self.run_chain(job, model_path, options);
+                        finished.lock().unwrap_or_else(|e| e.into_inner()).push((job.id.id, res, history));
+                    }
+                });
+            }
+        });
+
+        let mut finished = finished.into_inner().unwrap_or_else(|e| e.into_inner());
+        finished.sort_by_key(|(id, _, _)| *id);
+        for (id, r, history) in finished {
+            match r {
+                Ok(r) => res.succeeded.push((id, r)),
+                Err(e) => res.failed.push((id, e)),
+            }
+            if !history.is_empty() {
+                res.retried.push((id, history));
+            }
+        }
+        if let Some(dir) = res.run_dir.as_mut().filter(|_| !res.failed.is_empty()) {
+            dir.keep();
+        }
+        res
+    }
+}
+
+impl ChainRunner<ArgSample> {
+    /// Run all chains in one process, using the `num_chains` argument of sample.
+    pub fn run_single_process(&self, model_path: &ArgPath) -> Result<StanResult<WithCommonArgs<ArgSample>>, ArgError> {
+        let mut args = self.base.clone();
+        args.root.set_num_chains(self.num_chains)?;
+        arg_into_with_options(&args, model_path, &self.options, |_| {})
+    }
+}
+
+#[cfg(test)]
+mod runner_test {
+    use super::*;
+
+    #[test]
+    fn test_chain_args() {
+        let mut base = WithCommonArgs::new(ArgSample::new());
+        base.id.set_id(3);
+        base.output.set_file(ArgWritablePath::Borrowed("out/fit.csv"));
+        base.output.set_diagnostic_file(ArgWritablePath::Borrowed("out/diag.csv"));
+        let runner = ChainRunner::new(base, 4);
+
+        let chain = runner.chain_args(1, 100);
+        assert_eq!(chain.id.id, 4);
+        assert_eq!(chain.random.seed, Some(101));
+        assert_eq!(chain.output.file.as_path(), std::path::Path::new("out/fit_4.csv"));
+        assert_eq!(chain.output.diagnostic_file.as_path(), std::path::Path::new("out/diag_4.csv"));
+        assert!(chain.output.profile_file.is_default());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shared_run_dir() {
+        let parent = std::env::temp_dir().join(format!("simple_cmdstan_runner_{}", std::process::id()));
+        let options = RunOptions::new().with_run_dir(Some(crate::stan_command::RunDirPolicy::new(&parent).with_delete_on_drop(true)));
+        let runner = ChainRunner::new(WithCommonArgs::new(ArgSample::new()), 2).with_options(options);
+        // `true` writes no output file, so both chains fail and the directory is kept
+        let res = runner.run(&ArgPath::from("true"));
+        assert_eq!(res.failed.len(), 2);
+        let dirs: Vec<_> = std::fs::read_dir(&parent).unwrap().map(|e| e.unwrap().path()).collect();
+        assert_eq!(dirs.iter().map(|d| d.as_path()).collect::<Vec<_>>(), [res.run_dir.as_ref().unwrap().path()]);
+        drop(res);
+        assert!(dirs[0].exists());
+        std::fs::remove_dir_all(&parent).unwrap();
+    }
+
+    #[test]
+    fn test_retry_args() {
+        let runner = ChainRunner::new(WithCommonArgs::new(ArgSample::new()), 4);
+        let chain = runner.chain_args(1, 100);
+
+        let narrow = RetryPolicy::new(3, RetryStrategy::NarrowRange(0.5)).unwrap();
+        let second = narrow.next_args(&chain, 1, 4);
+        let third = narrow.next_args(&second, 2, 4);
+        assert_eq!((second.random.seed, third.random.seed), (Some(105), Some(109)));
+        assert_eq!((&second.init, &third.init), (&ArgInit::Range(1.0), &ArgInit::Range(0.5)));
+
+        let generate = RetryPolicy::new(3, RetryStrategy::Generator(Arc::new(|id, attempt| ArgInit::Range(f64::from(id * 10 + attempt))))).unwrap();
+        assert_eq!(generate.next_args(&chain, 1, 4).init, ArgInit::Range(21.0));
+
+        for factor in [0.0, -0.5, 1.0, 2.0, f64::NAN] {
+            assert!(RetryPolicy::new(3, RetryStrategy::NarrowRange(factor)).is_err(), "{factor}");
+        }
+    }
+}
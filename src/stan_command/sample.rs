@@ -100,6 +100,192 @@ impl ArgThrough for ArgSample {
         }
         Ok(())
     }
+
+    fn collect_problems(&self, problems: &mut Vec<String>) {
+        if self.thin > self.num_samples {
+            problems.push(format!("Sample: thin ({}) exceeds num_samples ({})", self.thin, self.num_samples));
+        }
+        if let Some(windows) = self.windows_exceeding_warmup()
+            && self.adapt.has_custom_windows() {
+            problems.push(format!("Sample: init_buffer + term_buffer + window ({windows}) exceeds num_warmup ({})", self.num_warmup));
+        }
+    }
+
+    fn collect_warnings(&self, warnings: &mut Vec<String>) {
+        if let Some(windows) = self.windows_exceeding_warmup()
+            && !self.adapt.has_custom_windows() {
+            warnings.push(format!("Sample: the default adaptation windows ({windows}) exceed num_warmup ({}), \
+                CmdStan shrinks them to 15%/75%/10% of num_warmup", self.num_warmup));
+        }
+    }
+
+    fn collect_min_versions(&self, res: &mut Vec<(&'static str, CmdStanVersion)>) {
+        if self.num_chains != 1 {
+            res.push(("sample num_chains", CmdStanVersion::new(2, 28, 0)));
+        }
+        if self.adapt.save_metric {
+            res.push(("sample adapt save_metric", CmdStanVersion::new(2, 34, 0)));
+        }
+    }
+
+    fn num_chains(&self) -> u32 {
+        self.num_chains
+    }
+
+    fn saves_metric(&self) -> bool {
+        self.adapt.save_metric
+    }
+}
+
+impl FromArgNode for ArgSample {
+    const METHOD: Option<&'static str> = Some("sample");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let node = node.expect_name("sample")?;
+        let mut res = Self::ARG_DEFAULT;
+        arg_from!(node.{num_samples, num_warmup, save_warmup, thin, num_chains} >> res);
+        if let Some(adapt) = node.child("adapt") {
+            res.adapt = ArgSampleAdapt::from_arg_node(adapt)?;
+        }
+        if let Some(algorithm) = node.child("algorithm") {
+            res.algorithm = ArgSampleAlgorithm::from_arg_node(algorithm)?;
+        }
+        Ok(res)
+    }
+}
+
+impl FromArgNode for ArgSampleAdapt {
+    const NAME: Option<&'static str> = Some("adapt");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let node = node.expect_name("adapt")?;
+        let mut res = Self::ARG_DEFAULT;
+        arg_from!(node.{engaged, gamma, delta, kappa, t0, init_buffer, term_buffer, window, save_metric} >> res);
+        Ok(res)
+    }
+}
+
+/// built from the choice `algorithm = hmc`
+impl FromArgNode for ArgSampleAlgorithm {
+    const NAME: Option<&'static str> = Some("algorithm");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        match node.expect_name("algorithm")?.chosen() {
+            Some(("fixed_param", _)) => Ok(Self::FixedParam),
+            Some(("hmc", hmc)) => Ok(Self::Hmc(hmc.map(ArgSampleHmc::from_arg_node).transpose()?.unwrap_or_default())),
+            _ => Err(ArgError::BadArgumentValue(format!("sample: unknown algorithm {:?}", node.value))),
+        }
+    }
+}
+
+impl FromArgNode for ArgSampleHmc {
+    const NAME: Option<&'static str> = Some("hmc");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let node = node.expect_name("hmc")?;
+        let mut res = Self::ARG_DEFAULT;
+        arg_from!(node.{metric_file, stepsize, stepsize_jitter} >> res);
+        if let Some(engine) = node.child("engine") {
+            res.engine = ArgSampleEngine::from_arg_node(engine)?;
+        }
+        if let Some(metric) = node.child("metric") {
+            res.metric = ArgSampleMetric::from_arg_node(metric)?;
+        }
+        Ok(res)
+    }
+}
+
+/// built from the choice `engine = nuts`
+impl FromArgNode for ArgSampleEngine {
+    const NAME: Option<&'static str> = Some("engine");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        match node.expect_name("engine")?.chosen() {
+            Some(("nuts", nuts)) => {
+                let mut max_depth = 10;
+                if let Some(nuts) = nuts {
+                    nuts.read_value("max_depth", &mut max_depth)?;
+                }
+                Ok(Self::Nuts(max_depth))
+            }
+            Some(("static", st)) => {
+                let mut int_time = DEFAULT_ENGINE_STATIC_VAL;
+                if let Some(st) = st {
+                    st.read_value("int_time", &mut int_time)?;
+                }
+                Ok(Self::Static(int_time))
+            }
+            _ => Err(ArgError::BadArgumentValue(format!("sample: unknown engine {:?}", node.value))),
+        }
+    }
+}
+
+impl FromArgNode for ArgSampleMetric {
+    const NAME: Option<&'static str> = Some("metric");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        match node.expect_name("metric")?.value.as_deref() {
+            None | Some("diag_e") => Ok(Self::DiagE),
+            Some("unit_e") => Ok(Self::UnitE),
+            Some("dense_e") => Ok(Self::DenseE),
+            Some(x) => Err(ArgError::BadArgumentValue(format!("sample: unknown metric {x}"))),
+        }
+    }
+}
+
+impl ToArgNode for ArgSample {
+    fn to_arg_node(&self) -> ArgNode {
+        let mut children = Vec::from(arg_to!(self.{num_samples, num_warmup, save_warmup, thin} in Self));
+        children.push(self.adapt.to_arg_node());
+        children.push(self.algorithm.to_arg_node());
+        children.extend(arg_to!(self.{num_chains} in Self));
+        ArgNode::group("sample", children)
+    }
+}
+
+impl ToArgNode for ArgSampleAdapt {
+    fn to_arg_node(&self) -> ArgNode {
+        ArgNode::group("adapt", arg_to!(self.{engaged, gamma, delta, kappa, t0, init_buffer, term_buffer, window, save_metric} in Self))
+    }
+}
+
+impl ToArgNode for ArgSampleAlgorithm {
+    fn to_arg_node(&self) -> ArgNode {
+        match self {
+            Self::FixedParam => ArgNode::choice("algorithm", "fixed_param", "hmc", None),
+            Self::Hmc(hmc) => ArgNode::choice("algorithm", "hmc", "hmc", Some(hmc.to_arg_node())),
+        }
+    }
+}
+
+impl ToArgNode for ArgSampleHmc {
+    fn to_arg_node(&self) -> ArgNode {
+        let mut children = vec![self.engine.to_arg_node(), self.metric.to_arg_node()];
+        children.extend(arg_to!(self.{metric_file, stepsize, stepsize_jitter} in Self));
+        ArgNode::group("hmc", children)
+    }
+}
+
+impl ToArgNode for ArgSampleEngine {
+    fn to_arg_node(&self) -> ArgNode {
+        match *self {
+            Self::Nuts(x) => ArgNode::choice("engine", "nuts", "nuts",
+                Some(ArgNode::group("nuts", vec![ArgNode::value("max_depth", &x, &10)]))),
+            Self::Static(x) => ArgNode::choice("engine", "static", "nuts",
+                Some(ArgNode::group("static", vec![ArgNode::value("int_time", &x, &DEFAULT_ENGINE_STATIC_VAL)]))),
+        }
+    }
+}
+
+impl ToArgNode for ArgSampleMetric {
+    fn to_arg_node(&self) -> ArgNode {
+        let metric = match self {
+            Self::UnitE => "unit_e",
+            Self::DiagE => "diag_e",
+            Self::DenseE => "dense_e",
+        };
+        ArgNode::value("metric", &metric, &"diag_e")
+    }
 }
 
 impl ArgSample {
@@ -109,13 +295,42 @@ impl ArgSample {
 
     default_setter!{
         <"Number of sampling iterations">(num_samples:u32; num_samples==0 => "Sample: num_samples could not be 0".to_string());
-        <"Number of warmup iterations">(num_warmup: u32; num_warmup==0 => "Sample: num_warmup could not be 0".to_string());
         <"Stream warmup samples to output?">(save_warmup: bool;);
         <"Period between saved samples">(thin: u32; thin==0 => "Sample: period between saved samples could not be 0".to_string());
         <"Warmup Adaptation">(adapt: ArgSampleAdapt;); 
         <"Sampling algorithm">(algorithm: ArgSampleAlgorithm;);
         <"Number of chains">(num_chains: u32; num_chains==0 => "Sample: num of chains could not be 0".to_string());
     }
+
+    /// Number of warmup iterations
+    ///
+    /// # Errors
+    ///
+    /// - when `num_warmup==0` while the adaptation is engaged returns BadArgumentValue, disengage it first
+    pub fn set_num_warmup(&mut self, num_warmup: u32) -> Result<&mut Self, ArgError> {
+        if num_warmup == 0 && self.adapt.engaged {
+            return Err(ArgError::BadArgumentValue("Sample: num_warmup could not be 0 while the adaptation is engaged".to_string()));
+        }
+        self.num_warmup = num_warmup;
+        Ok(self)
+    }
+
+    /// Number of warmup iterations
+    ///
+    /// # Errors
+    ///
+    /// - when `num_warmup==0` while the adaptation is engaged returns BadArgumentValue, disengage it first
+    pub fn with_num_warmup(mut self, num_warmup: u32) -> Result<Self, ArgError> {
+        self.set_num_warmup(num_warmup)?;
+        Ok(self)
+    }
+
+    /// init_buffer + term_buffer + window, if the adaptation of hmc runs and they exceed num_warmup
+    fn windows_exceeding_warmup(&self) -> Option<u32> {
+        let adapt = &self.adapt;
+        let windows = adapt.init_buffer.saturating_add(adapt.term_buffer).saturating_add(adapt.window);
+        (adapt.engaged && self.algorithm.get_hmc().is_some() && windows > self.num_warmup).then_some(windows)
+    }
 }
 
 impl ArgSampleAdapt {
@@ -134,6 +349,11 @@ impl ArgSampleAdapt {
         <"Initial width of slow adaptation interval">(window: u32; window==0 => format!("Sample: set_window expected window>0, found {}",window));
         <"Save metric as JSON?">(save_metric: bool;);
     }
+
+    /// whether any of init_buffer, term_buffer and window differs from its default
+    pub fn has_custom_windows(&self) -> bool {
+        (self.init_buffer, self.term_buffer, self.window) != (Self::ARG_DEFAULT.init_buffer, Self::ARG_DEFAULT.term_buffer, Self::ARG_DEFAULT.window)
+    }
 }
 
 impl ArgSampleAlgorithm {
@@ -44,6 +44,72 @@ impl ArgThrough for ArgVariational {
     }
 }
 
+impl FromArgNode for ArgVariational {
+    const METHOD: Option<&'static str> = Some("variational");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let node = node.expect_name("variational")?;
+        let mut res = Self::ARG_DEFAULT;
+        arg_from!(node.{iter, grad_samples, elbo_samples, eta, tol_rel_obj, eval_elbo, output_samples} >> res);
+        if let Some(algorithm) = node.child("algorithm") {
+            res.algorithm = ArgVariationalAlgorithm::from_arg_node(algorithm)?;
+        }
+        if let Some(adapt) = node.child("adapt") {
+            res.adapt = ArgVariationalAdapt::from_arg_node(adapt)?;
+        }
+        Ok(res)
+    }
+}
+
+impl FromArgNode for ArgVariationalAlgorithm {
+    const NAME: Option<&'static str> = Some("algorithm");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        match node.expect_name("algorithm")?.value.as_deref() {
+            None | Some("meanfield") => Ok(Self::Meanfield),
+            Some("fullrank") => Ok(Self::Fullrank),
+            Some(x) => Err(ArgError::BadArgumentValue(format!("variational: unknown algorithm {x}"))),
+        }
+    }
+}
+
+impl FromArgNode for ArgVariationalAdapt {
+    const NAME: Option<&'static str> = Some("adapt");
+
+    fn from_arg_node(node: &ArgNode) -> Result<Self, ArgError> {
+        let node = node.expect_name("adapt")?;
+        let mut res = Self::ARG_DEFAULT;
+        arg_from!(node.{engaged, iter} >> res);
+        Ok(res)
+    }
+}
+
+impl ToArgNode for ArgVariational {
+    fn to_arg_node(&self) -> ArgNode {
+        let mut children = vec![self.algorithm.to_arg_node()];
+        children.extend(arg_to!(self.{iter, grad_samples, elbo_samples, eta} in Self));
+        children.push(self.adapt.to_arg_node());
+        children.extend(arg_to!(self.{tol_rel_obj, eval_elbo, output_samples} in Self));
+        ArgNode::group("variational", children)
+    }
+}
+
+impl ToArgNode for ArgVariationalAlgorithm {
+    fn to_arg_node(&self) -> ArgNode {
+        let algorithm = match self {
+            Self::Meanfield => "meanfield",
+            Self::Fullrank => "fullrank",
+        };
+        ArgNode::choice("algorithm", algorithm, "meanfield", None)
+    }
+}
+
+impl ToArgNode for ArgVariationalAdapt {
+    fn to_arg_node(&self) -> ArgNode {
+        ArgNode::group("adapt", arg_to!(self.{engaged, iter} in Self))
+    }
+}
+
 impl ArgVariational {
     pub fn new() -> Self {
         Self::ARG_DEFAULT
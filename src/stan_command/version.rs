@@ -0,0 +1,91 @@
+use crate::init::STAN_HOME_KEY;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+/// A CmdStan release, such as `2.36.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CmdStanVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl CmdStanVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// read `CMDSTAN_VERSION` from the makefile of the CmdStan home `home`
+    pub fn from_makefile(home: &Path) -> Option<Self> {
+        std::fs::read_to_string(home.join("makefile")).ok()?
+            .lines()
+            .find_map(|l| l.trim().strip_prefix("CMDSTAN_VERSION"))
+            .and_then(|v| v.trim_start_matches([' ', ':', '=']).trim().parse().ok())
+    }
+
+    /// the version of the CmdStan home set by `init`
+    pub fn installed() -> Option<Self> {
+        Self::from_makefile(&PathBuf::from(std::env::var_os(STAN_HOME_KEY)?))
+    }
+
+    /// run `<model> info` and read the `stan_version_*` lines, Stan and CmdStan are released together
+    pub fn from_model_info(model_path: &Path) -> Option<Self> {
+        let output = Command::new(model_path).arg("info").output().ok()?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        let field = |name: &str| text.lines()
+            .find_map(|l| l.trim().strip_prefix(name)?.trim_start().strip_prefix('=')?.trim().parse().ok());
+        Some(Self::new(field("stan_version_major")?, field("stan_version_minor")?, field("stan_version_patch").unwrap_or(0)))
+    }
+
+    /// Detect the version running `model_path`: the installed CmdStan, else the model's `info`.
+    ///
+    /// The `info` of each model is only asked once.
+    pub fn detect(model_path: &Path) -> Option<Self> {
+        if let Some(v) = Self::installed() {
+            return Some(v);
+        }
+        static FROM_INFO: OnceLock<Mutex<HashMap<PathBuf, Option<CmdStanVersion>>>> = OnceLock::new();
+        let mut cache = FROM_INFO.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+        *cache.entry(model_path.to_path_buf()).or_insert_with(|| Self::from_model_info(model_path))
+    }
+}
+
+impl Display for CmdStanVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// parse `2.36.0`, `2.36` or `v2.36.0`
+impl FromStr for CmdStanVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("bad CmdStan version {s}");
+        let mut parts = s.trim().trim_start_matches('v').splitn(3, '.');
+        let mut next = |required: bool| match parts.next() {
+            Some(p) => p.trim().split(|c: char| !c.is_ascii_digit()).next().unwrap_or_default().parse().map_err(|_| err()),
+            None if required => Err(err()),
+            None => Ok(0),
+        };
+        Ok(Self::new(next(true)?, next(true)?, next(false)?))
+    }
+}
+
+#[cfg(test)]
+mod version_test {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!("2.36.0".parse(), Ok(CmdStanVersion::new(2, 36, 0)));
+        assert_eq!("v2.33".parse(), Ok(CmdStanVersion::new(2, 33, 0)));
+        assert_eq!("2.34.1-rc1".parse(), Ok(CmdStanVersion::new(2, 34, 1)));
+        assert!("nightly".parse::<CmdStanVersion>().is_err());
+        assert!(CmdStanVersion::new(2, 9, 0) < CmdStanVersion::new(2, 33, 0));
+    }
+}
@@ -0,0 +1,254 @@
+use super::arg_tree::*;
+use super::common_arg::WithCommonArgs;
+use super::sample::{ArgSample, ArgSampleMetric};
+use super::fit::{SampleFit, InvMetric};
+use crate::prelude::{DataEntry, DataEntries};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// What to do with the warmup of a warm-started run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WarmupPolicy {
+    /// run the warmup as set
+    Keep,
+    /// no warmup and no adaptation, sample with the step size and metric of the fit
+    Disable,
+    /// Run this many warmup iterations, which must not be 0.
+    ///
+    /// The adaptation windows are shrunk the way CmdStan does when they don't fit:
+    /// 15% init buffer, 10% term buffer, and the rest for the slow window.
+    Shorten(u32),
+}
+
+/// How `warm_start` reuses a previous fit.
+///
+/// Bare file names, such as the defaults, are put next to the output file of the run, so that runs
+/// writing to different directories don't overwrite each other's files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarmStart {
+    /// where the inverse metric of the fit is written, as `{"inv_metric": ...}`
+    pub metric_file: ArgWritablePath,
+    /// where the inits taken from the last draw are written
+    pub init_file: ArgWritablePath,
+    pub warmup: WarmupPolicy,
+}
+
+impl WithDefaultArg for WarmStart {
+    const ARG_DEFAULT: Self = Self {
+        metric_file: ArgWritablePath::Borrowed("warm_start_metric.json"),
+        init_file: ArgWritablePath::Borrowed("warm_start_init.json"),
+        warmup: WarmupPolicy::Keep,
+    };
+}
+
+impl Default for WarmStart {
+    fn default() -> Self {
+        Self::ARG_DEFAULT
+    }
+}
+
+impl WarmStart {
+    pub fn new() -> Self {
+        Self::ARG_DEFAULT
+    }
+
+    default_setter!{
+        <"Where the inverse metric of the fit is written">(metric_file: ArgWritablePath;);
+        <"Where the inits taken from the last draw are written">(init_file: ArgWritablePath;);
+        <"What to do with the warmup">(warmup: WarmupPolicy;);
+    }
+}
+
+/// the 1-based index and the value of each cell of a variable
+type Cells = Vec<(Vec<usize>, f64)>;
+
+/// Turn a draw into inits, one entry for each variable, skipping the `__` columns.
+///
+/// Columns of a container, such as `beta.2` or `Sigma.1.2`, are put back into nested arrays;
+/// `z.real` and `z.imag` into a complex. Columns of tuples (`x:1`) are skipped, CmdStan draws their inits.
+pub fn inits_from_draw(columns: &[String], draw: &[f64]) -> Vec<(String, DataEntry)> {
+    let mut vars: BTreeMap<&str, (usize, Cells)> = BTreeMap::new();
+    for (i, (column, value)) in columns.iter().zip(draw).enumerate() {
+        if column.ends_with("__") || column.contains(':') {
+            continue;
+        }
+        let mut parts = column.split('.');
+        let name = parts.next().unwrap_or_default();
+        // complex parts are the last index, real first
+        let index: Option<Vec<usize>> = parts.map(|p| match p {
+            "real" => Some(1),
+            "imag" => Some(2),
+            p => p.parse().ok(),
+        }).collect();
+        if let Some(index) = index {
+            vars.entry(name).or_insert((i, Vec::new())).1.push((index, *value));
+        }
+    }
+
+    let mut res: Vec<_> = vars.into_iter().map(|(name, (first, cells))| (first, name, cells)).collect();
+    res.sort_by_key(|(first, _, _)| *first);
+    res.into_iter().map(|(_, name, cells)| {
+        let is_complex = columns.iter().any(|c| c.strip_prefix(name).is_some_and(|c| c.starts_with('.') && c.ends_with(".imag")));
+        (name.to_string(), nest(&cells, &[], is_complex))
+    }).collect()
+}
+
+/// the entry of the cells whose index starts with `prefix`
+fn nest(cells: &[(Vec<usize>, f64)], prefix: &[usize], is_complex: bool) -> DataEntry {
+    let depth = prefix.len();
+    let below: Vec<_> = cells.iter().filter(|(index, _)| index.starts_with(prefix)).collect();
+    match below.iter().map(|(index, _)| index.len()).max() {
+        Some(len) if len > depth => {}
+        _ => return DataEntry::Real(below.first().map(|(_, v)| *v).unwrap_or_default()),
+    }
+    if is_complex && below.iter().all(|(index, _)| index.len() == depth + 1) {
+        let part = |p| below.iter().find(|(index, _)| index[depth] == p).map(|(_, v)| *v).unwrap_or_default();
+        return DataEntry::Complex((part(1), part(2)));
+    }
+    let size = below.iter().map(|(index, _)| index[depth]).max().unwrap_or_default();
+    let mut next = prefix.to_vec();
+    DataEntry::Array((1..=size).map(|i| {
+        next.truncate(depth);
+        next.push(i);
+        nest(cells, &next, is_complex)
+    }).collect())
+}
+
+impl WithCommonArgs<ArgSample> {
+    /// Start from the adaptation and the last draw of a previous fit.
+    ///
+    /// The inverse metric is written to `how.metric_file`, next to the output file if it's a bare file name,
+    /// and passed as `metric_file`; the metric is set
+    /// to `diag_e` or `dense_e` to match it, and the adapted step size is used. The inits are the last draw.
+    ///
+    /// ```no-run
+    /// let fit = res.fit()?;
+    /// let mut args = res.arg_tree.clone();
+    /// args.warm_start(&fit, &WarmStart::new().with_warmup(WarmupPolicy::Shorten(100)))?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - ArgError::BadArgumentValue if the fit has no adaptation or no draws, the sampler isn't hmc,
+    ///   or the warmup is shortened to 0 iterations
+    /// - ArgError::FileSystemError if the metric file can't be written
+    pub fn warm_start(&mut self, fit: &SampleFit, how: &WarmStart) -> Result<&mut Self, ArgError> {
+        let adaptation = fit.adaptation.as_ref()
+            .ok_or_else(|| ArgError::BadArgumentValue("warm start: the fit has no adaptation".to_string()))?;
+        let last = fit.draws.rows.last()
+            .ok_or_else(|| ArgError::BadArgumentValue("warm start: the fit has no draws".to_string()))?;
+        let hmc = self.root.algorithm.get_mut_hmc()
+            .ok_or_else(|| ArgError::BadArgumentValue("warm start: only hmc can be warm started".to_string()))?;
+
+        if let Some(inv_metric) = &adaptation.inv_metric {
+            let (metric, json) = match inv_metric {
+                InvMetric::Diag(d) => (ArgSampleMetric::DiagE, serde_json::json!({"inv_metric": d})),
+                InvMetric::Dense(d) => (ArgSampleMetric::DenseE, serde_json::json!({"inv_metric": d})),
+            };
+            let metric_file = how.metric_file.resolved_in(self.output.file.as_path().parent().unwrap_or(Path::new("")));
+            metric_file.write_once(&json.to_string()).map_err(ArgError::FileSystemError)?;
+            hmc.metric = metric;
+            hmc.metric_file = ArgReadablePath::Owned(metric_file.as_path().to_path_buf());
+        }
+        hmc.set_stepsize(adaptation.stepsize)?;
+
+        let mut inits = DataEntries::new();
+        for (name, entry) in inits_from_draw(&fit.draws.columns, last) {
+            inits.add_entry(&name, entry);
+        }
+        self.init.target_init_by_param_path(how.init_file.clone()).set_init_by_entries(inits);
+
+        match how.warmup {
+            WarmupPolicy::Keep => {}
+            WarmupPolicy::Disable => {
+                self.root.adapt.engaged = false;
+                self.root.set_num_warmup(0)?;
+            }
+            WarmupPolicy::Shorten(0) => {
+                return Err(ArgError::BadArgumentValue("warm start: cannot shorten the warmup to 0 iterations, use WarmupPolicy::Disable".to_string()));
+            }
+            WarmupPolicy::Shorten(n) => {
+                self.root.set_num_warmup(n)?;
+                let adapt = &mut self.root.adapt;
+                if adapt.init_buffer.saturating_add(adapt.term_buffer).saturating_add(adapt.window) > n {
+                    adapt.init_buffer = (u64::from(n) * 15 / 100) as u32;
+                    adapt.term_buffer = n / 10;
+                    adapt.window = n - adapt.init_buffer - adapt.term_buffer;
+                }
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod warm_start_test {
+    use super::*;
+    use crate::stan_command::common_arg::ArgInit;
+    use crate::stan_command::sample::ArgSampleAdapt;
+    use crate::stan_command::fit::Adaptation;
+    use crate::result_analyzer::StanCsv;
+
+    #[test]
+    fn test_inits_from_draw() {
+        let columns: Vec<String> = ["lp__", "mu", "Sigma.1.1", "Sigma.2.1", "Sigma.1.2", "Sigma.2.2", "z.real", "z.imag", "t:1"]
+            .iter().map(|c| c.to_string()).collect();
+        let inits = inits_from_draw(&columns, &[-1.0, 0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+        assert_eq!(inits, [
+            ("mu".to_string(), DataEntry::Real(0.5)),
+            ("Sigma".to_string(), DataEntry::from(vec![vec![1.0, 3.0], vec![2.0, 4.0]])),
+            ("z".to_string(), DataEntry::Complex((5.0, 6.0))),
+        ]);
+    }
+
+    #[test]
+    fn test_warm_start() {
+        let dir = std::env::temp_dir().join(format!("simple_cmdstan_warm_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fit = SampleFit {
+            warmup: StanCsv::default(),
+            draws: StanCsv::parse("lp__,mu\n-1,0.1\n-2,0.2\n").unwrap(),
+            adaptation: Some(Adaptation { stepsize: 0.7, inv_metric: Some(InvMetric::Diag(vec![0.5])) }),
+            elapsed: None,
+        };
+        let how = WarmStart::new().with_warmup(WarmupPolicy::Shorten(100));
+        let mut args = WithCommonArgs::new(ArgSample::new());
+        args.output.set_file(ArgWritablePath::Owned(dir.join("output.csv")));
+        args.warm_start(&fit, &how).unwrap();
+
+        let hmc = args.root.algorithm.expect_hmc();
+        let metric_file = dir.join("warm_start_metric.json");
+        assert_eq!((hmc.stepsize, hmc.metric_file.as_path()), (0.7, metric_file.as_path()));
+        assert_eq!(std::fs::read_to_string(&metric_file).unwrap(), r#"{"inv_metric":[0.5]}"#);
+        assert_eq!((args.root.num_warmup, args.root.adapt.init_buffer, args.root.adapt.term_buffer, args.root.adapt.window), (100, 15, 10, 75));
+        assert!(args.validate().is_ok());
+        match &args.init {
+            ArgInit::ParamValue((inits, file)) => {
+                assert_eq!(inits.get_entry("mu"), Some(&DataEntry::Real(0.2)));
+                assert_eq!(file, &ArgWritablePath::Borrowed("warm_start_init.json"));
+            }
+            init => panic!("expected ParamValue, found {init:?}"),
+        }
+
+        // 15% of a long warmup doesn't overflow
+        args.root.adapt.set_window(u32::MAX).unwrap();
+        args.warm_start(&fit, &how.clone().with_warmup(WarmupPolicy::Shorten(1_000_000_000))).unwrap();
+        let adapt = &args.root.adapt;
+        assert_eq!((adapt.init_buffer, adapt.term_buffer, adapt.window), (150_000_000, 100_000_000, 750_000_000));
+
+        // a warmup can't be shortened to nothing, but it can be disabled
+        assert!(args.warm_start(&fit, &how.clone().with_warmup(WarmupPolicy::Shorten(0))).is_err());
+        args.warm_start(&fit, &how.clone().with_warmup(WarmupPolicy::Disable)).unwrap();
+        assert_eq!((args.root.num_warmup, args.root.adapt.engaged), (0, false));
+        assert!(args.root.clone().with_adapt(ArgSampleAdapt::new()).set_num_warmup(0).is_err());
+
+        // the inits replace those of a previous init, which keeps the file set by `how`
+        args.init.set_init_per_chain(|_| DataEntries::new());
+        args.init.set_init_by_param("sigma", 1.0);
+        args.warm_start(&fit, &how.clone().with_init_file(ArgWritablePath::Borrowed("warm.json"))).unwrap();
+        let mut expected = DataEntries::new();
+        expected.add_entry("mu", 0.2);
+        assert_eq!(args.init, ArgInit::ParamValue((expected, ArgWritablePath::Borrowed("warm.json"))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
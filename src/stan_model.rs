@@ -1,7 +1,18 @@
 mod wrapper;
 mod wrapper_builder;
+mod content_hash;
+mod inline_source;
+mod build_cache;
+mod build_lock;
+mod stanc;
+mod lint;
+mod workspace;
 
-use crate::{arg_paths::{ArgPath, ArgReadablePath}, stan_command::{arg_into, ArgThrough, StanResult}, error::{ParamError, FileError, CmdStanError}};
+pub use lint::{StanLint, StanLintKind};
+pub use workspace::WorkspaceUsage;
+pub(crate) use content_hash::ContentHasher;
+
+use crate::{arg_paths::{ArgPath, ArgReadablePath}, stan_command::{arg_into, arg_into_with_progress, arg_into_with_options, ArgThrough, StanResult, StanEvent, RunOptions, ChainRunner, ChainResults}, error::{ParamError, FileError, CmdStanError}};
 use std::path::PathBuf;
 
 pub trait WithParam {
@@ -31,6 +42,26 @@ pub trait WithExecutable {
         arg_into(arg_tree, &self.get_model_executable()).map_err(CmdStanError::Arg)
     }
 
+    /// see: crate::stan_command::arg_into_with_progress
+    fn arg_into_with_progress<T:ArgThrough+Clone, F:FnMut(StanEvent)>(&mut self, arg_tree: &T, on_event: F) -> Result<StanResult<T>, CmdStanError> {
+        self.compile().map_err(CmdStanError::File)?;
+        arg_into_with_progress(arg_tree, &self.get_model_executable(), on_event).map_err(CmdStanError::Arg)
+    }
+
+    /// see: crate::stan_command::arg_into_with_options
+    fn arg_into_with_options<T:ArgThrough+Clone, F:FnMut(StanEvent)>(&mut self, arg_tree: &T, options: &RunOptions, on_event: F) -> Result<StanResult<T>, CmdStanError> {
+        self.compile().map_err(CmdStanError::File)?;
+        arg_into_with_options(arg_tree, &self.get_model_executable(), options, on_event).map_err(CmdStanError::Arg)
+    }
+
+    /// compile the model, then run the chains of `runner` on it
+    ///
+    /// see: crate::stan_command::ChainRunner::run
+    fn run_chains<T:ArgThrough+Clone+Send+Sync>(&mut self, runner: &ChainRunner<T>) -> Result<ChainResults<T>, CmdStanError> {
+        self.compile().map_err(CmdStanError::File)?;
+        Ok(runner.run(&self.get_model_executable()))
+    }
+
     /// called before every get_model_excutable()
     /// 
     /// return Ok(()) if the compile is successful, otherwise return Err(ArgError)
@@ -46,4 +77,47 @@ pub trait WithData {
     fn dump_data(&mut self) -> Result<(), FileError> {
         Ok(())
     }
+}
+
+/// Run stanc alone on the model, which is much faster than a full `make`.
+pub trait WithStanc: WithPath {
+    /// extra flags passed to every stanc call, such as `--include-paths`
+    fn get_stanc_flags(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Type-check the program without building the C++ code.
+    ///
+    /// return the warnings printed by stanc, or Err(FileError::Stanc) if the program is invalid.
+    fn check_syntax(&self) -> Result<String, FileError> {
+        stanc::check_syntax(self.get_model_path().as_path(), &self.get_stanc_flags())
+    }
+
+    /// Run the pedantic analysis of stanc (`--warn-pedantic`), which finds likely modelling mistakes
+    /// such as parameters without priors or unused parameters.
+    fn pedantic_lints(&self) -> Result<Vec<StanLint>, FileError> {
+        lint::pedantic_lints(self.get_model_path().as_path(), &self.get_stanc_flags())
+    }
+
+    /// return the source formatted by `stanc --auto-format`
+    fn auto_format(&self) -> Result<String, FileError> {
+        stanc::format(self.get_model_path().as_path(), &self.get_stanc_flags(), false)
+    }
+
+    /// return the source printed by `stanc --print-canonical`, which also updates deprecated syntax
+    fn print_canonical(&self) -> Result<String, FileError> {
+        stanc::format(self.get_model_path().as_path(), &self.get_stanc_flags(), true)
+    }
+
+    /// rewrite the .stan file with `auto_format()`
+    fn auto_format_in_place(&self) -> Result<(), FileError> {
+        let formatted = self.auto_format()?;
+        std::fs::write(self.get_model_path().as_path(), formatted).map_err(FileError::FileSystem)
+    }
+
+    /// rewrite the .stan file with `print_canonical()`
+    fn print_canonical_in_place(&self) -> Result<(), FileError> {
+        let formatted = self.print_canonical()?;
+        std::fs::write(self.get_model_path().as_path(), formatted).map_err(FileError::FileSystem)
+    }
 }
\ No newline at end of file
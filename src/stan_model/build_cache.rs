@@ -0,0 +1,286 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::env::consts::OS;
+use crate::init::STAN_HOME_KEY;
+use crate::stan_command::CmdStanVersion;
+use super::*;
+use super::content_hash::ContentHasher;
+
+/// A build cache shared between workspaces.
+///
+/// Compiled executables are stored in `<root>/<key>/`, where `<key>` is the hash of the Stan source,
+/// the files it includes, the compile arguments and the CmdStan version. A cached executable is
+/// hard-linked (or copied, if linking fails) into the workspace instead of being rebuilt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildCache {
+    root: PathBuf,
+}
+
+impl Default for BuildCache {
+    fn default() -> Self {
+        Self::new(&Self::default_root())
+    }
+}
+
+impl BuildCache {
+    pub fn new(root: &Path) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn default_root() -> PathBuf {
+        std::env::temp_dir().join("simple-cmdstan-rs").join("build_cache")
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn entry_executable(&self, key: &str) -> PathBuf {
+        let mut res = self.root.join(key).join("model");
+        if OS == "windows" {
+            res.set_extension("exe");
+        }
+        res
+    }
+
+    /// Compute the cache key of a model.
+    ///
+    /// The key covers the source file, every file it `#include`s (searched in the source directory and
+    /// in the `--include-paths` of `STANCFLAGS`), the user header, the compile arguments in their order
+    /// (a later `make` argument overrides an earlier one) and the CmdStan version.
+    ///
+    /// # Errors
+    ///
+    /// - when the source or an included file cannot be read, returns FileError::FileSystem
+    /// - when the CmdStan home is not set, returns FileError::EnvVar
+    pub fn key(source: &Path, compile_args: &[(String, String)]) -> Result<String, FileError> {
+        key_of(source, compile_args, &cmdstan_version_tag()?)
+    }
+
+    /// Link (or copy) the cached executable of `key` to `target`.
+    ///
+    /// return Ok(false) if nothing is cached for `key`.
+    pub fn install(&self, key: &str, target: &Path) -> Result<bool, FileError> {
+        let cached = self.entry_executable(key);
+        if !cached.is_file() {
+            return Ok(false);
+        }
+        if target.exists() {
+            std::fs::remove_file(target).map_err(FileError::FileSystem)?;
+        }
+        if std::fs::hard_link(&cached, target).is_err() {
+            std::fs::copy(&cached, target).map_err(FileError::FileSystem)?;
+        }
+        // the executable must look newer than the source, and the entry is now the most recently used one
+        touch(target).map_err(FileError::FileSystem)?;
+        touch(&cached).map_err(FileError::FileSystem)?;
+        Ok(true)
+    }
+
+    /// Store a freshly built executable under `key`.
+    pub fn store(&self, key: &str, executable: &Path) -> Result<PathBuf, FileError> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let cached = self.entry_executable(key);
+        if let Some(p) = cached.parent() {
+            std::fs::create_dir_all(p).map_err(FileError::FileSystem)?;
+        }
+        // copy then rename, so that a reader never sees a half-written executable;
+        // the same key may be stored by several threads building in different workspaces
+        let mut tmp = cached.clone();
+        tmp.set_extension(format!("tmp{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+        std::fs::copy(executable, &tmp).map_err(FileError::FileSystem)?;
+        std::fs::rename(&tmp, &cached).map_err(|e| {
+            let _ = std::fs::remove_file(&tmp);
+            FileError::FileSystem(e)
+        })?;
+        Ok(cached)
+    }
+
+    /// Remove cache entries.
+    ///
+    /// - entries not used for longer than `max_age` are removed;
+    /// - then the least recently used entries are removed until the cache is no larger than `max_size` bytes.
+    ///
+    /// return the removed entry directories.
+    pub fn prune(&self, max_age: Option<Duration>, max_size: Option<u64>) -> Result<Vec<PathBuf>, FileError> {
+        let mut removed = Vec::new();
+        if !self.root.is_dir() {
+            return Ok(removed);
+        }
+
+        let now = SystemTime::now();
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&self.root).map_err(FileError::FileSystem)? {
+            let path = entry.map_err(FileError::FileSystem)?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let (size, last_used) = entry_usage(&path).map_err(FileError::FileSystem)?;
+            entries.push((path, size, last_used));
+        }
+        entries.sort_by_key(|(_, _, last_used)| *last_used);
+
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        for (path, size, last_used) in entries {
+            let too_old = max_age.is_some_and(|age| now.duration_since(last_used).unwrap_or_default() > age);
+            let too_large = max_size.is_some_and(|max| total > max);
+            if too_old || too_large {
+                std::fs::remove_dir_all(&path).map_err(FileError::FileSystem)?;
+                total -= size;
+                removed.push(path);
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// the cache key of `source` built with `compile_args` by the CmdStan tagged `cmdstan`
+fn key_of(source: &Path, compile_args: &[(String, String)], cmdstan: &str) -> Result<String, FileError> {
+    let mut hasher = ContentHasher::new();
+    hasher.update_field(&std::fs::read(source).map_err(FileError::FileSystem)?);
+
+    let mut include_dirs: Vec<PathBuf> = source.parent().map(PathBuf::from).into_iter().collect();
+    for (key, val) in compile_args {
+        if key == "STANCFLAGS" {
+            include_dirs.extend(include_paths_of_stancflags(val));
+        }
+    }
+    let mut visited = HashSet::new();
+    hash_includes(source, &include_dirs, &mut visited, &mut hasher)?;
+
+    if let Some((_, header)) = compile_args.iter().find(|(key, _)| key == "USER_HEADER") {
+        hasher.update_field(&std::fs::read(header).map_err(FileError::FileSystem)?);
+    }
+
+    for (key, val) in compile_args {
+        hasher.update_field(key.as_bytes()).update_field(val.as_bytes());
+    }
+
+    hasher.update_field(cmdstan.as_bytes());
+    Ok(hasher.finish_hex())
+}
+
+fn touch(path: &Path) -> std::io::Result<()> {
+    std::fs::OpenOptions::new().write(true).open(path)?.set_modified(SystemTime::now())
+}
+
+fn entry_usage(dir: &Path) -> std::io::Result<(u64, SystemTime)> {
+    let mut size = 0;
+    let mut last_used = SystemTime::UNIX_EPOCH;
+    for entry in std::fs::read_dir(dir)? {
+        let meta = entry?.metadata()?;
+        size += meta.len();
+        last_used = last_used.max(meta.modified()?);
+    }
+    Ok((size, last_used))
+}
+
+fn include_paths_of_stancflags(flags: &str) -> Vec<PathBuf> {
+    flags.split_whitespace()
+        .filter_map(|f| f.strip_prefix("--include-paths=").or_else(|| f.strip_prefix("--include_paths=")))
+        .flat_map(|f| f.split(','))
+        .map(PathBuf::from)
+        .collect()
+}
+
+fn hash_includes(file: &Path, include_dirs: &[PathBuf], visited: &mut HashSet<PathBuf>, hasher: &mut ContentHasher) -> Result<(), FileError> {
+    let text = std::fs::read_to_string(file).map_err(FileError::FileSystem)?;
+    for line in text.lines() {
+        let Some(name) = line.trim().strip_prefix("#include") else {
+            continue;
+        };
+        let name = name.trim().trim_matches(|c| c == '"' || c == '<' || c == '>');
+        let found = include_dirs.iter().map(|d| d.join(name)).find(|p| p.is_file());
+        if let Some(path) = found
+            && visited.insert(path.clone()) {
+            hasher.update_field(name.as_bytes());
+            hasher.update_field(&std::fs::read(&path).map_err(FileError::FileSystem)?);
+            hash_includes(&path, include_dirs, visited, hasher)?;
+        }
+    }
+    Ok(())
+}
+
+/// the version of CmdStan home, or its path when the version is unknown
+fn cmdstan_version_tag() -> Result<String, FileError> {
+    let home = PathBuf::from(std::env::var(STAN_HOME_KEY).map_err(FileError::EnvVar)?);
+    Ok(CmdStanVersion::from_makefile(&home)
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| home.to_string_lossy().into_owned()))
+}
+
+#[cfg(test)]
+mod build_cache_test {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("simple-cmdstan-rs-cache-test-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_key() {
+        let dir = test_dir("key");
+        let source = dir.join("model.stan");
+        std::fs::write(&source, "#include \"funs.stan\"\nparameters { real y; }").unwrap();
+        std::fs::write(dir.join("funs.stan"), "functions { real f() { return 1; } }").unwrap();
+        let args = [("STAN_THREADS".to_string(), "true".to_string()), ("O".to_string(), "3".to_string())];
+
+        let key = key_of(&source, &args, "2.36.0").unwrap();
+        assert_eq!(key_of(&source, &args, "2.36.0").unwrap(), key);
+        assert_ne!(key_of(&source, &args, "2.35.0").unwrap(), key);
+        let reversed = [args[1].clone(), args[0].clone()];
+        assert_ne!(key_of(&source, &reversed, "2.36.0").unwrap(), key);
+
+        std::fs::write(dir.join("funs.stan"), "functions { real f() { return 2; } }").unwrap();
+        assert_ne!(key_of(&source, &args, "2.36.0").unwrap(), key);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_store() {
+        let dir = test_dir("install");
+        let cache = BuildCache::new(&dir.join("cache"));
+        let executable = dir.join("model");
+        let target = dir.join("installed");
+        std::fs::write(&executable, "built").unwrap();
+
+        assert!(!cache.install("k", &target).unwrap());
+        let cached = cache.store("k", &executable).unwrap();
+        assert_eq!(std::fs::read_to_string(&cached).unwrap(), "built");
+        assert!(cache.install("k", &target).unwrap());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "built");
+
+        // threads storing the same key don't trip over each other's temporary file
+        std::thread::scope(|s| {
+            let handles: Vec<_> = (0..8).map(|_| s.spawn(|| cache.store("k", &executable))).collect();
+            assert!(handles.into_iter().all(|h| h.join().unwrap().is_ok()));
+        });
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune() {
+        let dir = test_dir("prune");
+        let cache = BuildCache::new(&dir);
+        let now = SystemTime::now();
+        let hour = Duration::from_secs(3600);
+        for (name, age) in [("old", 48), ("older", 24), ("recent", 2), ("new", 1)] {
+            let file = dir.join(name).join("model");
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(&file, [0; 100]).unwrap();
+            std::fs::File::options().write(true).open(&file).unwrap().set_modified(now - hour * age).unwrap();
+        }
+
+        let removed = cache.prune(Some(hour * 36), None).unwrap();
+        assert_eq!(removed, [dir.join("old")]);
+        // then the least recently used entry goes until at most 250 bytes are left
+        let removed = cache.prune(None, Some(250)).unwrap();
+        assert_eq!(removed, [dir.join("older")]);
+        assert!(dir.join("recent").is_dir() && dir.join("new").is_dir());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs::{File, TryLockError};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use super::*;
+
+/// How long `compile` waits for another build of the same executable by default.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(600);
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An advisory lock on `<executable>.lock`, held while the executable is being built.
+///
+/// The lock is released when the guard is dropped (or when the holding process dies).
+#[derive(Debug)]
+pub struct BuildLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl BuildLock {
+    pub fn lock_path(executable: &Path) -> PathBuf {
+        let mut name = executable.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+        name.push(".lock");
+        executable.with_file_name(name)
+    }
+
+    /// Wait until the build lock of `executable` is acquired.
+    ///
+    /// # Errors
+    ///
+    /// - when the lock is not acquired within `timeout`, returns FileError::LockTimeout
+    /// - when the lock file cannot be opened, returns FileError::FileSystem
+    pub fn acquire(executable: &Path, timeout: Duration) -> Result<Self, FileError> {
+        let path = Self::lock_path(executable);
+        if let Some(p) = path.parent() {
+            std::fs::create_dir_all(p).map_err(FileError::FileSystem)?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(FileError::FileSystem)?;
+
+        let start = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Self { file, path }),
+                Err(TryLockError::WouldBlock) => {
+                    if start.elapsed() >= timeout {
+                        return Err(FileError::LockTimeout(path, timeout));
+                    }
+                    std::thread::sleep(LOCK_POLL_INTERVAL.min(timeout.saturating_sub(start.elapsed())));
+                }
+                Err(TryLockError::Error(e)) => return Err(FileError::FileSystem(e)),
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for BuildLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod build_lock_test {
+    use super::*;
+
+    #[test]
+    fn test_lock_timeout() {
+        let dir = std::env::temp_dir().join(format!("simple-cmdstan-rs-lock-test-{}", std::process::id()));
+        let executable = dir.join("model");
+        let held = BuildLock::acquire(&executable, Duration::ZERO).unwrap();
+        assert_eq!(held.path(), dir.join("model.lock"));
+
+        let waiting = BuildLock::acquire(&executable, Duration::from_millis(200));
+        assert!(matches!(waiting, Err(FileError::LockTimeout(_, _))));
+
+        drop(held);
+        assert!(BuildLock::acquire(&executable, Duration::ZERO).is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,47 @@
+/// A small, stable (FNV-1a, 64 bit) hasher used to address generated files on disk.
+///
+/// `std::hash::DefaultHasher` is not guaranteed to be stable between Rust releases,
+/// so it cannot be used to name directories that outlive the process.
+#[derive(Debug, Clone)]
+pub struct ContentHasher(u64);
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+impl Default for ContentHasher {
+    fn default() -> Self {
+        Self(FNV_OFFSET)
+    }
+}
+
+impl ContentHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) -> &mut Self {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+        self
+    }
+
+    /// update with a length prefix, so that ("ab","c") and ("a","bc") hash differently
+    pub fn update_field(&mut self, bytes: &[u8]) -> &mut Self {
+        self.update(&(bytes.len() as u64).to_le_bytes());
+        self.update(bytes)
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+
+    pub fn finish_hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+pub fn content_hash_hex(bytes: &[u8]) -> String {
+    ContentHasher::new().update(bytes).finish_hex()
+}
@@ -0,0 +1,99 @@
+use crate::StanData;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use super::*;
+use super::wrapper::CmdStanModel;
+use super::content_hash::content_hash_hex;
+use super::build_lock::DEFAULT_LOCK_TIMEOUT;
+
+/// The directory which holds the workspaces of models created from inline source.
+pub fn default_source_root() -> PathBuf {
+    std::env::temp_dir().join("simple-cmdstan-rs").join("models")
+}
+
+impl<T, D:StanData> CmdStanModel<T, D> {
+    /// Create a model from Stan source code, using `default_source_root()` as the managed workspace root.
+    ///
+    /// see: `CmdStanModel::from_source_in`
+    pub fn from_source(model: T, source: &str) -> Result<Self, FileError> {
+        Self::from_source_in(model, source, &default_source_root())
+    }
+
+    /// Create a model from Stan source code.
+    ///
+    /// The source is written into `<root>/<hash>/model_<hash>.stan`, where `<hash>` is the hash of the source,
+    /// and then compiled. Requesting the same source again reuses the workspace and, if it's still up to date,
+    /// the compiled executable.
+    ///
+    /// ```no-run
+    /// let model: CmdStanModel<()> = CmdStanModel::from_source((), "parameters { real y; } model { y ~ std_normal(); }")?;
+    /// ```
+    pub fn from_source_in(model: T, source: &str, root: &Path) -> Result<Self, FileError> {
+        let (workspace, model_name) = write_source(source, root)?;
+        let mut res = Self {
+            model,
+            workspace_path: ArgPath::Owned(workspace),
+            model_name,
+            data_path: None,
+            data: None,
+            compiled: false,
+            compile_args: Vec::new(),
+            build_cache: None,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            build_dir: None,
+        };
+
+        res.compile()?;
+        Ok(res)
+    }
+}
+
+/// Write `source` into its content-addressed workspace under `root`, return the workspace and the model name.
+///
+/// The file is written to a temporary file and renamed into place, so that concurrent calls with the same
+/// source never see a half-written file. An up-to-date file is left untouched, keeping its modified time.
+fn write_source(source: &str, root: &Path) -> Result<(PathBuf, String), FileError> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let hash = content_hash_hex(source.as_bytes());
+    let workspace = root.join(&hash);
+    let model_name = format!("model_{hash}");
+    std::fs::create_dir_all(&workspace).map_err(FileError::FileSystem)?;
+
+    let source_path = workspace.join(&model_name).with_extension("stan");
+    if std::fs::read_to_string(&source_path).is_ok_and(|s| s == source) {
+        return Ok((workspace, model_name));
+    }
+    let tmp = source_path.with_extension(format!("stan.tmp{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+    std::fs::write(&tmp, source).map_err(FileError::FileSystem)?;
+    std::fs::rename(&tmp, &source_path).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp);
+        FileError::FileSystem(e)
+    })?;
+    Ok((workspace, model_name))
+}
+
+#[cfg(test)]
+mod inline_source_test {
+    use super::*;
+
+    #[test]
+    fn test_write_source() {
+        let root = std::env::temp_dir().join(format!("simple-cmdstan-rs-source-test-{}", std::process::id()));
+        let source = "parameters { real y; } model { y ~ std_normal(); }";
+        let (workspace, name) = write_source(source, &root).unwrap();
+        let hash = content_hash_hex(source.as_bytes());
+        assert_eq!((workspace.clone(), name.clone()), (root.join(&hash), format!("model_{hash}")));
+        let path = workspace.join(&name).with_extension("stan");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), source);
+
+        // the same source reuses the file as it is
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        assert_eq!(write_source(source, &root).unwrap(), (workspace.clone(), name));
+        assert_eq!(std::fs::metadata(&path).unwrap().modified().unwrap(), modified);
+        assert_eq!(std::fs::read_dir(&workspace).unwrap().count(), 1);
+
+        let (other, _) = write_source("parameters { real z; }", &root).unwrap();
+        assert_ne!(other, workspace);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,184 @@
+use std::path::Path;
+use super::*;
+
+/// The kind of a warning reported by `stanc --warn-pedantic`.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StanLintKind {
+    /// a parameter has no prior
+    NoPrior,
+    /// a parameter has more than one prior
+    MultiplePriors,
+    /// a parameter is declared but never used in the density
+    UnusedParameter,
+    /// a uniform distribution is used, which is usually an improper or too hard prior
+    UniformDistribution,
+    /// a parameter has hard (non-infinite) bounds
+    HardConstraint,
+    /// a distribution argument that must be positive is not constrained to be positive
+    UnconstrainedScale,
+    /// large or small constants suggest the parameters are not on unit scale
+    NonUnitScale,
+    /// a control flow statement depends on parameters
+    ParameterDependentControlFlow,
+    /// a variable may be used before it is assigned
+    UnassignedVariable,
+    /// an integer division which might be intended as real division
+    IntegerDivision,
+    /// any other warning
+    Other,
+}
+
+impl StanLintKind {
+    fn classify(message: &str) -> Self {
+        let m = message.to_lowercase();
+        if m.contains("has no priors") {
+            Self::NoPrior
+        } else if m.contains("priors") && (m.contains("has 2") || m.contains("more than one")) {
+            Self::MultiplePriors
+        } else if m.contains("was declared but was not used") || m.contains("not used in the density") {
+            Self::UnusedParameter
+        } else if m.contains("uniform distribution") {
+            Self::UniformDistribution
+        } else if m.contains("hard constraints") {
+            Self::HardConstraint
+        } else if m.contains("strictly positive") || m.contains("not constrained to be") {
+            Self::UnconstrainedScale
+        } else if m.contains("suggests there may be parameters that are not unit scale") {
+            Self::NonUnitScale
+        } else if m.contains("control flow") {
+            Self::ParameterDependentControlFlow
+        } else if m.contains("before its use") || m.contains("not have been assigned") {
+            Self::UnassignedVariable
+        } else if m.contains("integer division") || m.contains("int division") {
+            Self::IntegerDivision
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// A warning of `stanc --warn-pedantic`, with its location if stanc reported one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StanLint {
+    pub kind: StanLintKind,
+    pub file: Option<PathBuf>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+impl std::fmt::Display for StanLint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.to_string_lossy())?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "{line}:")?;
+        }
+        if let Some(column) = self.column {
+            write!(f, "{column}:")?;
+        }
+        write!(f, " {:?}: {}", self.kind, self.message)
+    }
+}
+
+/// Parse the warnings printed by stanc.
+///
+/// A warning starts with `Warning` (`Warning in 'model.stan', line 4, column 2: message`)
+/// and continues on the following lines until the next warning or a blank line.
+pub fn parse_lints(stanc_stderr: &str) -> Vec<StanLint> {
+    let mut res: Vec<StanLint> = Vec::new();
+    let mut open = false;
+    for line in stanc_stderr.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("Warning") {
+            res.push(parse_lint_head(trimmed));
+            open = true;
+        } else if trimmed.is_empty() {
+            open = false;
+        } else if open && let Some(last) = res.last_mut() {
+            if !last.message.is_empty() {
+                last.message.push(' ');
+            }
+            last.message.push_str(trimmed);
+        }
+    }
+    for lint in &mut res {
+        lint.kind = StanLintKind::classify(&lint.message);
+    }
+    res
+}
+
+fn parse_lint_head(line: &str) -> StanLint {
+    let mut lint = StanLint { kind: StanLintKind::Other, file: None, line: None, column: None, message: String::new() };
+    let rest = line.trim_start_matches("Warning").trim_start_matches(':').trim();
+    let Some(location) = rest.strip_prefix("in ").or_else(|| rest.strip_prefix("at ")) else {
+        lint.message = rest.to_string();
+        return lint;
+    };
+
+    // location: 'file', line 4, column 2 to column 10: message
+    let (location, message) = match location.find(": ") {
+        Some(i) => (&location[..i], location[i+2..].trim()),
+        None => (location, ""),
+    };
+    lint.message = message.to_string();
+    for part in location.split(',') {
+        let part = part.trim();
+        if let Some(file) = part.strip_prefix('\'').and_then(|p| p.strip_suffix('\'')) {
+            lint.file = Some(PathBuf::from(file));
+        } else if let Some(n) = part.strip_prefix("line ") {
+            lint.line = n.split_whitespace().next().and_then(|n| n.parse().ok());
+        } else if let Some(n) = part.strip_prefix("column ") {
+            lint.column = n.split_whitespace().next().and_then(|n| n.parse().ok());
+        }
+    }
+    lint
+}
+
+/// run `stanc --warn-pedantic` on the model and return the lints
+pub fn pedantic_lints(model_path: &Path, flags: &[String]) -> Result<Vec<StanLint>, FileError> {
+    let mut flags = flags.to_vec();
+    flags.push("--warn-pedantic".to_string());
+    flags.push("--info".to_string());
+    let output = stanc::run_stanc(model_path, &flags)?;
+    Ok(parse_lints(&String::from_utf8_lossy(&output.stderr)))
+}
+
+#[cfg(test)]
+mod lint_test {
+    use super::*;
+
+    #[test]
+    fn test_parse_lints() {
+        let stderr = "\
+Warning in 'bernoulli.stan', line 5, column 2: The parameter sigma has no
+    priors. This means either no prior is provided, or the prior(s) depend on
+    data variables.
+
+Warning in 'bernoulli.stan', line 4, column 2: The parameter tau was declared
+    but was not used in the density calculation.
+Warning: The parameter theta has 2 priors.
+";
+        let lints = parse_lints(stderr);
+        assert_eq!(lints.len(), 3);
+        assert_eq!(lints[0].kind, StanLintKind::NoPrior);
+        assert_eq!(lints[0].file, Some(PathBuf::from("bernoulli.stan")));
+        assert_eq!(lints[0].line, Some(5));
+        assert_eq!(lints[0].column, Some(2));
+        assert!(lints[0].message.ends_with("depend on data variables."));
+        assert_eq!(lints[1].kind, StanLintKind::UnusedParameter);
+        assert_eq!(lints[1].line, Some(4));
+        assert_eq!(lints[2].kind, StanLintKind::MultiplePriors);
+        assert_eq!(lints[2].line, None);
+    }
+
+    #[test]
+    fn test_classify_scale() {
+        let scale = "Argument 10000 suggests there may be parameters that are not unit scale; \
+            consider rescaling with a multiplier (see manual section 22.12).";
+        assert_eq!(StanLintKind::classify(scale), StanLintKind::NonUnitScale);
+        assert_eq!(StanLintKind::classify("The data x is given on unit scale."), StanLintKind::Other);
+    }
+}
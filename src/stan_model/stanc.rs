@@ -0,0 +1,104 @@
+use std::path::Path;
+use std::process::{Command, Output};
+use std::env::consts::OS;
+use crate::init::STAN_HOME_KEY;
+use super::*;
+
+/// `<CmdStan home>/bin/stanc`
+pub fn stanc_path() -> Result<PathBuf, FileError> {
+    let mut res = PathBuf::from(std::env::var(STAN_HOME_KEY).map_err(FileError::EnvVar)?).join("bin").join("stanc");
+    if OS == "windows" {
+        res.set_extension("exe");
+    }
+    Ok(res)
+}
+
+/// split the `STANCFLAGS` compile argument into separate flags
+pub fn stanc_flags_of(compile_args: &[(String, String)]) -> Vec<String> {
+    compile_args.iter()
+        .filter(|(key, _)| key == "STANCFLAGS")
+        .flat_map(|(_, val)| val.split_whitespace().map(String::from))
+        .collect()
+}
+
+/// Add `dir` to the `--include-paths` of `STANCFLAGS`.
+///
+/// stanc keeps only the last `--include-paths`, so the paths of every occurrence are merged into one flag,
+/// followed by `dir`.
+pub fn merge_include_path(flags: &str, dir: &str) -> String {
+    let (includes, mut res): (Vec<&str>, Vec<&str>) = flags.split_whitespace()
+        .partition(|f| f.starts_with("--include-paths=") || f.starts_with("--include_paths="));
+    let mut paths: Vec<&str> = includes.iter()
+        .filter_map(|f| f.split_once('=').map(|(_, paths)| paths))
+        .flat_map(|paths| paths.split(','))
+        .filter(|p| !p.is_empty())
+        .collect();
+    paths.push(dir);
+    let include = format!("--include-paths={}", paths.join(","));
+    res.push(&include);
+    res.join(" ")
+}
+
+/// Run stanc on `model_path` in the directory of the model, so that relative `#include`s resolve.
+///
+/// # Errors
+///
+/// - when stanc cannot be found, returns FileError::FileSystem
+/// - when stanc exits unsuccessfully, returns FileError::Stanc with its output
+pub fn run_stanc(model_path: &Path, flags: &[String]) -> Result<Output, FileError> {
+    // stanc runs in the model directory, where a relative CmdStan home would point elsewhere
+    let stanc = stanc_path()?.canonicalize().map_err(FileError::FileSystem)?;
+    let mut command = Command::new(stanc);
+    if let Some(p) = model_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        command.current_dir(p);
+    }
+    let file_name = model_path.file_name()
+        .ok_or_else(|| FileError::InvalidPath("No file name founded".to_string(), model_path.into()))?;
+    command.args(flags).arg(file_name);
+
+    let output = command.output().map_err(FileError::FileSystem)?;
+    if !output.status.success() {
+        Err(FileError::Stanc(output))
+    } else {
+        Ok(output)
+    }
+}
+
+/// Type-check the program without generating C++. return the warnings printed by stanc.
+pub fn check_syntax(model_path: &Path, flags: &[String]) -> Result<String, FileError> {
+    let mut flags = flags.to_vec();
+    flags.push("--info".to_string());
+    let output = run_stanc(model_path, &flags)?;
+    Ok(String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+/// return the program formatted by `--auto-format`, or by `--print-canonical` if `canonical` is true
+pub fn format(model_path: &Path, flags: &[String], canonical: bool) -> Result<String, FileError> {
+    let mut flags = flags.to_vec();
+    flags.push(if canonical { "--print-canonical" } else { "--auto-format" }.to_string());
+    let output = run_stanc(model_path, &flags)?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod stanc_test {
+    use super::*;
+
+    #[test]
+    fn test_stanc_flags_of() {
+        let compile_args = [
+            ("STANCFLAGS".to_string(), " --include-paths=a,b  --O1".to_string()),
+            ("STAN_THREADS".to_string(), "true".to_string()),
+            ("STANCFLAGS".to_string(), "--warn-uninitialized".to_string()),
+        ];
+        assert_eq!(stanc_flags_of(&compile_args), ["--include-paths=a,b", "--O1", "--warn-uninitialized"]);
+        assert!(stanc_flags_of(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_merge_include_path() {
+        assert_eq!(merge_include_path("", "/ws"), "--include-paths=/ws");
+        assert_eq!(merge_include_path("--O1 --include-paths=a,b", "/ws"), "--O1 --include-paths=a,b,/ws");
+        assert_eq!(merge_include_path("--include_paths=a --O1 --include-paths=b", "/ws"), "--O1 --include-paths=a,b,/ws");
+    }
+}
@@ -0,0 +1,164 @@
+use crate::StanData;
+use std::path::Path;
+use super::*;
+use super::wrapper::CmdStanModel;
+use super::build_lock::BuildLock;
+
+/// Disk usage of a model workspace, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkspaceUsage {
+    /// all files in the workspace directory (recursively)
+    pub workspace: u64,
+    /// all files in the build directory, 0 if the model builds into its workspace
+    pub build_dir: u64,
+    /// the generated build artifacts, counted in `workspace` or `build_dir` as well
+    pub artifacts: u64,
+}
+
+impl WorkspaceUsage {
+    pub fn total(&self) -> u64 {
+        self.workspace + self.build_dir
+    }
+}
+
+impl<T, D:StanData> CmdStanModel<T, D> {
+    /// Move the build outputs to `dir`, keeping the source workspace free of generated files.
+    ///
+    /// The model is marked as not compiled; an up-to-date executable already in `dir` is reused by `compile()`.
+    pub fn set_build_dir(&mut self, dir: ArgPath) -> &mut Self {
+        self.build_dir = Some(dir);
+        self.compiled = false;
+        self
+    }
+
+    /// the files the build may generate: `.hpp`, `.o`, `.d`, the executable and its build lock,
+    /// plus the copy of the source when a build directory is used
+    fn artifact_candidates(&self) -> Vec<PathBuf> {
+        let executable = PathBuf::from(self.executable_name());
+        let base = self.build_location().join(&self.model_name);
+        let mut res = vec![
+            base.with_extension("hpp"),
+            base.with_extension("o"),
+            base.with_extension("d"),
+            BuildLock::lock_path(&executable),
+            executable,
+        ];
+        if self.build_dir.is_some() {
+            res.push(base.with_extension("stan"));
+        }
+        res
+    }
+
+    /// list the generated build artifacts which currently exist
+    pub fn build_artifacts(&self) -> Vec<PathBuf> {
+        self.artifact_candidates().into_iter().filter(|p| p.is_file()).collect()
+    }
+
+    /// Remove the generated build artifacts and mark the model as not compiled.
+    ///
+    /// return the removed files.
+    pub fn clean_build_artifacts(&mut self) -> Result<Vec<PathBuf>, FileError> {
+        let res = self.build_artifacts();
+        for p in &res {
+            std::fs::remove_file(p).map_err(FileError::FileSystem)?;
+        }
+        self.compiled = false;
+        Ok(res)
+    }
+
+    /// report the disk usage of the workspace and the build directory
+    pub fn disk_usage(&self) -> Result<WorkspaceUsage, FileError> {
+        let mut res = WorkspaceUsage {
+            workspace: dir_size(self.workspace_path.as_path()).map_err(FileError::FileSystem)?,
+            ..Default::default()
+        };
+        if let Some(dir) = &self.build_dir {
+            res.build_dir = dir_size(dir.as_path()).map_err(FileError::FileSystem)?;
+        }
+        for p in self.build_artifacts() {
+            res.artifacts += std::fs::metadata(p).map_err(FileError::FileSystem)?.len();
+        }
+        Ok(res)
+    }
+
+    /// copy the .stan file into the build directory if the copy is missing or outdated
+    pub(crate) fn sync_source_to_build_dir(&self) -> Result<(), FileError> {
+        let location = self.build_location();
+        std::fs::create_dir_all(&location).map_err(FileError::FileSystem)?;
+        let mut target = location.join(&self.model_name);
+        target.set_extension("stan");
+        let source = std::fs::read(self.get_model_path().as_path()).map_err(FileError::FileSystem)?;
+        if std::fs::read(&target).ok().as_ref() != Some(&source) {
+            std::fs::write(&target, source).map_err(FileError::FileSystem)?;
+        }
+        Ok(())
+    }
+}
+
+fn dir_size(dir: &Path) -> std::io::Result<u64> {
+    if !dir.is_dir() {
+        return Ok(0);
+    }
+    let mut res = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        res += if meta.is_dir() { dir_size(&entry.path())? } else { meta.len() };
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod workspace_test {
+    use super::*;
+    use crate::prelude::DataEntries;
+    use super::super::build_lock::DEFAULT_LOCK_TIMEOUT;
+
+    #[test]
+    fn test_build_artifacts() {
+        let dir = std::env::temp_dir().join(format!("simple-cmdstan-rs-workspace-test-{}", std::process::id()));
+        let (workspace, build) = (dir.join("workspace"), dir.join("build"));
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::write(workspace.join("model.stan"), "parameters { real y; }").unwrap();
+        let mut model: CmdStanModel<(), DataEntries> = CmdStanModel {
+            model: (),
+            workspace_path: ArgPath::Owned(workspace.clone()),
+            model_name: "model".to_string(),
+            data_path: None,
+            data: None,
+            compiled: false,
+            compile_args: Vec::new(),
+            build_cache: None,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            build_dir: None,
+        };
+        model.set_build_dir(ArgPath::Owned(build.clone()));
+        model.sync_source_to_build_dir().unwrap();
+        let executable = PathBuf::from(model.executable_name());
+        for (file, size) in [(build.join("model.hpp"), 100), (build.join("model.o"), 1000), (executable.clone(), 2000), (build.join("notes.txt"), 10)] {
+            std::fs::write(file, vec![0; size]).unwrap();
+        }
+
+        let mut artifacts = model.build_artifacts();
+        artifacts.sort();
+        let mut expected = vec![build.join("model.hpp"), build.join("model.o"), build.join("model.stan"), executable];
+        expected.sort();
+        assert_eq!(artifacts, expected);
+
+        let source = "parameters { real y; }".len() as u64;
+        assert_eq!(model.disk_usage().unwrap(), WorkspaceUsage {
+            workspace: source,
+            build_dir: 3110 + source,
+            artifacts: 3100 + source,
+        });
+
+        model.set_compiled();
+        let mut removed = model.clean_build_artifacts().unwrap();
+        removed.sort();
+        assert_eq!(removed, expected);
+        assert!(!model.compiled);
+        assert!(model.build_artifacts().is_empty());
+        assert!(build.join("notes.txt").is_file() && workspace.join("model.stan").is_file());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
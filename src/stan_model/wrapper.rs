@@ -1,9 +1,12 @@
 use crate::{prelude::DataEntries, StanData};
-use std::path::absolute;
+use std::path::{absolute, Path};
 use std::process::Command;
 use crate::init::STAN_HOME_KEY;
 use std::env::consts::OS;
 use super::*;
+use super::build_cache::BuildCache;
+use super::build_lock::BuildLock;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct CmdStanModel<T, D: StanData = DataEntries> {
@@ -13,22 +16,89 @@ pub struct CmdStanModel<T, D: StanData = DataEntries> {
     pub data_path: Option<ArgPath>,
     pub data: Option<D>,
     pub compiled: bool,
+    pub compile_args: Vec<(String, String)>,
+    pub build_cache: Option<BuildCache>,
+    pub lock_timeout: Duration,
+    pub build_dir: Option<ArgPath>,
 }
 
 impl<T, D: StanData> CmdStanModel<T, D> {
-    fn executable_name(&self) -> ArgPath {
-        let mut res = PathBuf::from(self.workspace_path.as_path()).join(&self.model_name);
+    /// the directory build outputs go to: the build directory if set, otherwise the workspace
+    pub fn build_location(&self) -> PathBuf {
+        match &self.build_dir {
+            Some(dir) => dir.as_path().into(),
+            None => self.workspace_path.as_path().into(),
+        }
+    }
+
+    pub(crate) fn executable_name(&self) -> ArgPath {
+        let mut res = self.build_location().join(&self.model_name);
         if OS == "windows" {
             res.set_extension("exe");
         }
         ArgPath::Owned(res)
     }
 
+    /// the user header passed to `make` by `USER_HEADER=...`, if any
+    pub fn user_header(&self) -> Option<PathBuf> {
+        self.compile_args.iter().find(|(key, _)| key == "USER_HEADER").map(|(_, val)| PathBuf::from(val))
+    }
+
     pub fn set_compiled(&mut self) -> &mut Self {
         self.compiled = true;
         self
     }
 
+    pub fn set_build_cache(&mut self, cache: BuildCache) -> &mut Self {
+        self.build_cache = Some(cache);
+        self
+    }
+
+    /// how long `compile` waits for a concurrent build of the same executable
+    pub fn set_lock_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// run `make` in CmdStan home on the executable, with the compile arguments of the model
+    fn make(&self) -> Result<(), FileError> {
+        // make doesn't know about the user header, so drop the object file when the header is newer
+        if let Some(header) = self.user_header() {
+            let object = self.executable_name().as_path().with_extension("o");
+            if let (Some(h), Some(o)) = (modified_time(&header), modified_time(&object))
+                && h > o {
+                std::fs::remove_file(&object).map_err(FileError::FileSystem)?;
+            }
+        }
+
+        let mut compile_args = self.compile_args.clone();
+        if self.build_dir.is_some() {
+            // make builds `<target>.stan` next to the target, so build a copy of the source in the build directory
+            self.sync_source_to_build_dir()?;
+            let include = absolute(self.workspace_path.as_path()).map_err(FileError::FileSystem)?;
+            // make keeps only the last STANCFLAGS and stanc the last --include-paths, so merge them into one of each
+            let flags = stanc::stanc_flags_of(&compile_args).join(" ");
+            compile_args.retain(|(key, _)| key != "STANCFLAGS");
+            compile_args.push(("STANCFLAGS".to_string(), stanc::merge_include_path(&flags, &include.to_string_lossy())));
+        }
+
+        let absolute_executable = absolute(self.executable_name().as_path()).map_err(FileError::FileSystem)?;
+        let mut command = Command::new("make");
+        command.current_dir(std::env::var(STAN_HOME_KEY).map_err(FileError::EnvVar)?)
+            .arg(absolute_executable);
+
+        for (key, val) in &compile_args {
+            command.arg(format!("{}={}",key,val));
+        }
+
+        let output = command.output().map_err(FileError::FileSystem)?;
+        if !output.status.success() {
+            Err(FileError::from_compilation(output))
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn set_data_path(&mut self, path: ArgPath) -> &mut Self {
         self.data_path = Some(path);
         self
@@ -57,6 +127,10 @@ impl<T, D: StanData> CmdStanModel<T, D> {
     }
 }
 
+fn modified_time(path: &Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
 impl<T:WithParam, D:StanData> WithParam for CmdStanModel<T, D> {
     fn get_param_name(&self) -> Vec<String> {
         self.model.get_param_name()
@@ -82,24 +156,35 @@ impl<T, D:StanData> WithPath for CmdStanModel<T, D> {
     }
 }
 
+impl<T, D:StanData> WithStanc for CmdStanModel<T, D> {
+    fn get_stanc_flags(&self) -> Vec<String> {
+        stanc::stanc_flags_of(&self.compile_args)
+    }
+}
+
 impl<T, D:StanData> WithExecutable for CmdStanModel<T, D> {
     fn compile(&mut self) -> Result<(), FileError> {
         if self.compiled {
             return Ok(());
         }
 
-        let absolute_executable = absolute(self.executable_name().as_path()).map_err(FileError::FileSystem)?;
-        let command = Command::new("make")
-            .current_dir(std::env::var(STAN_HOME_KEY).map_err(FileError::EnvVar)?)
-            .arg(absolute_executable)
-            .output().map_err(FileError::FileSystem)?;
-
-        if !command.status.success() {
-            Err(FileError::Compilation(command))
-        } else {
+        // concurrent builds of the same target corrupt each other, so wait for any other build to finish
+        let _lock = BuildLock::acquire(self.executable_name().as_path(), self.lock_timeout)?;
+        // no freshness check of our own: make tracks the included files, and the cache key covers them too
+        let Some(cache) = &self.build_cache else {
+            self.make()?;
             self.compiled = true;
-            Ok(())
+            return Ok(());
+        };
+
+        let executable = self.executable_name();
+        let key = BuildCache::key(self.get_model_path().as_path(), &self.compile_args)?;
+        if !cache.install(&key, executable.as_path())? {
+            self.make()?;
+            cache.store(&key, executable.as_path())?;
         }
+        self.compiled = true;
+        Ok(())
     }
 
     fn get_model_executable(&self) -> ArgPath {
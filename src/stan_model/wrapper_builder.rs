@@ -2,11 +2,12 @@ use std::collections::HashMap;
 use crate::stan_command::WithDefaultArg;
 use crate::{prelude::DataEntries, StanData};
 use std::path::{absolute, Path};
-use std::process::Command;
-use crate::init::STAN_HOME_KEY;
 use std::{env::consts::OS, ffi::OsStr};
 use super::*;
 use super::wrapper::CmdStanModel;
+use super::build_cache::BuildCache;
+use super::build_lock::DEFAULT_LOCK_TIMEOUT;
+use std::time::Duration;
 pub struct CmdStanModelBuilder<T, D:StanData = DataEntries> {
     model: T,
     workspace_path: ArgPath,
@@ -15,6 +16,12 @@ pub struct CmdStanModelBuilder<T, D:StanData = DataEntries> {
     data: Option<D>,
     compiled: bool,
     complie_arg: HashMap<String, String>,
+    build_cache: Option<BuildCache>,
+    lock_timeout: Duration,
+    denied_lints: Vec<StanLintKind>,
+    allow_undefined: bool,
+    user_header: Option<PathBuf>,
+    build_dir: Option<ArgPath>,
 }
 
 impl<T:Default, D:StanData> Default for CmdStanModelBuilder<T, D> {
@@ -27,6 +34,12 @@ impl<T:Default, D:StanData> Default for CmdStanModelBuilder<T, D> {
             data: None,
             compiled: false,
             complie_arg: HashMap::new(),
+            build_cache: None,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            denied_lints: Vec::new(),
+            allow_undefined: false,
+            user_header: None,
+            build_dir: None,
         }
     }
 }
@@ -51,6 +64,12 @@ impl<T, D:StanData> CmdStanModelBuilder<T, D> {
             data: None,
             compiled: false,
             complie_arg: HashMap::new(),
+            build_cache: None,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            denied_lints: Vec::new(),
+            allow_undefined: false,
+            user_header: None,
+            build_dir: None,
         }
     }
 
@@ -82,6 +101,48 @@ impl<T, D:StanData> CmdStanModelBuilder<T, D> {
         self
     }
 
+    /// share compiled executables through the given build cache
+    pub fn with_build_cache(mut self, cache: BuildCache) -> Self {
+        self.build_cache = Some(cache);
+        self
+    }
+
+    /// how long compiling waits for a concurrent build of the same executable, default: 10 minutes
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// make `build()` fail with FileError::Lint when `stanc --warn-pedantic` reports a lint of this kind
+    pub fn with_denied_lint(mut self, kind: StanLintKind) -> Self {
+        if !self.denied_lints.contains(&kind) {
+            self.denied_lints.push(kind);
+        }
+        self
+    }
+
+    /// let stanc accept functions which are declared but not defined in the model (`--allow-undefined`)
+    pub fn with_allow_undefined(mut self) -> Self {
+        self.allow_undefined = true;
+        self
+    }
+
+    /// Implement the undefined functions of the model by a C++ header.
+    ///
+    /// This implies `with_allow_undefined()`. The header is passed to make as `USER_HEADER`, and the model
+    /// is rebuilt when the header changes.
+    pub fn with_user_header(mut self, header: &Path) -> Self {
+        self.allow_undefined = true;
+        self.user_header = Some(header.into());
+        self
+    }
+
+    /// put the generated .hpp/.o/.d files and the executable in `build_dir` instead of the workspace
+    pub fn with_build_dir(mut self, build_dir: &Path) -> Self {
+        self.build_dir = Some(ArgPath::Owned(build_dir.into()));
+        self
+    }
+
     pub fn with_data_path(mut self, data_path: &Path) -> Self {
         self.data_path = Some(ArgPath::Owned(data_path.into()));
         self
@@ -102,41 +163,51 @@ impl<T, D:StanData> CmdStanModelBuilder<T, D> {
         self
     }
 
-    fn executable_name(&self) -> ArgPath {
-        let mut res = PathBuf::from(self.workspace_path.as_path()).join(&self.model_name);
-        if OS == "windows" {
-            res.set_extension("exe");
-        }
-        ArgPath::Owned(res)
-    }
-
     pub fn build(self) -> Result<CmdStanModel<T, D>, FileError> {
-        let mut compiled = self.compiled;
-        if !self.complie_arg.is_empty() {
-            let absolute_executable = absolute(self.executable_name().as_path()).map_err(FileError::FileSystem)?;
-            let mut command = Command::new("make");
-            command.current_dir(std::env::var(STAN_HOME_KEY).map_err(FileError::EnvVar)?)
-                .arg(absolute_executable);
-
-            for (key, val) in self.complie_arg.into_iter() {
-                command.arg(format!("{}={}",key,val));
-            }
-
-            let output = command.output().map_err(FileError::FileSystem)?;   
-            if !output.status.success() {
-                return Err(FileError::Compilation(output))
+        let mut complie_arg = self.complie_arg;
+        if self.allow_undefined {
+            let flags = complie_arg.entry("STANCFLAGS".to_string()).or_default();
+            if !flags.split_whitespace().any(|f| f == "--allow-undefined") {
+                if !flags.is_empty() {
+                    flags.push(' ');
+                }
+                flags.push_str("--allow-undefined");
             }
-
-            compiled = true;
+        }
+        if let Some(header) = &self.user_header {
+            let header = absolute(header).map_err(FileError::FileSystem)?;
+            complie_arg.insert("USER_HEADER".to_string(), header.to_string_lossy().into_owned());
         }
 
-        Ok(CmdStanModel {
+        let recompile = !complie_arg.is_empty();
+        let mut compile_args: Vec<(String, String)> = complie_arg.into_iter().collect();
+        compile_args.sort();
+
+        let mut model = CmdStanModel {
             model: self.model,
             workspace_path: self.workspace_path,
             model_name: self.model_name,
             data_path: self.data_path,
             data: self.data,
-            compiled,
-        })
+            compiled: self.compiled && !recompile,
+            compile_args,
+            build_cache: self.build_cache,
+            lock_timeout: self.lock_timeout,
+            build_dir: self.build_dir,
+        };
+
+        if !self.denied_lints.is_empty() {
+            let fired: Vec<_> = model.pedantic_lints()?.into_iter()
+                .filter(|l| self.denied_lints.contains(&l.kind))
+                .collect();
+            if !fired.is_empty() {
+                return Err(FileError::Lint(fired));
+            }
+        }
+
+        if recompile {
+            model.compile()?;
+        }
+        Ok(model)
     }
 }
\ No newline at end of file